@@ -0,0 +1,122 @@
+use axum::extract::FromRef;
+use axum_extra::extract::cookie::Key;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::highscores::HighscoreStore;
+use crate::saves::SaveStore;
+use crate::session::SessionStore;
+
+/// Shared application state handed to every router
+#[derive(Clone)]
+pub struct AppState {
+    pub sessions: SessionStore,
+    pub cookie_key: Key,
+    pub saves: SaveStore,
+    pub highscores: HighscoreStore,
+}
+
+impl AppState {
+    pub fn new(cookie_key: Key, saves: SaveStore, highscores: HighscoreStore) -> Self {
+        Self {
+            sessions: SessionStore::default(),
+            cookie_key,
+            saves,
+            highscores,
+        }
+    }
+
+    /// Uses `EMPEROR_SESSION_KEY` (at least 64 bytes) to sign session cookies; otherwise
+    /// the key stored in the save directory, generated on the first start. Either way
+    /// cookies, and with them the owners of the save slots, survive restarts.
+    /// Save slots live in `EMPEROR_SAVE_DIR` (default `saves/`) and finished games are
+    /// recorded in `EMPEROR_HIGHSCORES` (default `highscores.json`).
+    pub fn from_env() -> Self {
+        let save_dir = std::env::var("EMPEROR_SAVE_DIR").unwrap_or_else(|_| "saves".to_string());
+        let stored_key = || {
+            let path = Path::new(&save_dir).join(KEY_FILE);
+            stored_key(&path).unwrap_or_else(|err| {
+                tracing::warn!(
+                    "Could not keep the session key in {}: {}; saves will not survive a restart",
+                    path.display(),
+                    err
+                );
+                Key::generate()
+            })
+        };
+        let key = match std::env::var("EMPEROR_SESSION_KEY") {
+            Ok(secret) => Key::try_from(secret.as_bytes()).unwrap_or_else(|_| {
+                tracing::warn!(
+                    "EMPEROR_SESSION_KEY is shorter than 64 bytes, using the stored key instead"
+                );
+                stored_key()
+            }),
+            Err(_) => stored_key(),
+        };
+        let highscores_path =
+            std::env::var("EMPEROR_HIGHSCORES").unwrap_or_else(|_| "highscores.json".to_string());
+        Self::new(
+            key,
+            SaveStore::new(save_dir),
+            HighscoreStore::new(highscores_path),
+        )
+    }
+}
+
+/// File in the save directory holding the generated session key
+const KEY_FILE: &str = "session.key";
+
+/// Reads the key at `path`, or generates one and writes it there for the next start
+fn stored_key(path: &Path) -> io::Result<Key> {
+    match fs::read(path) {
+        Ok(bytes) => Key::try_from(bytes.as_slice())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "the stored key is too short")),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            let key = Key::generate();
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(path, key.master())?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+            }
+            Ok(key)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+impl FromRef<AppState> for SessionStore {
+    fn from_ref(state: &AppState) -> Self {
+        state.sessions.clone()
+    }
+}
+
+impl FromRef<AppState> for Key {
+    fn from_ref(state: &AppState) -> Self {
+        state.cookie_key.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_key_is_reused() {
+        let dir = std::env::temp_dir().join(format!("rust-emperor-key-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join(KEY_FILE);
+
+        let first = stored_key(&path).unwrap();
+        let second = stored_key(&path).unwrap();
+        assert_eq!(first.master(), second.master());
+
+        fs::write(&path, b"short").unwrap();
+        assert!(stored_key(&path).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,611 @@
+//! Terminal client
+//! Plays the same game as the web server from a text menu, e.g. over SSH:
+//! `cargo run --bin rust-emperor-cli`
+//! Like the server, it uses the balance file named by `EMPEROR_BALANCE`, if any, and
+//! the event files in `EMPEROR_EVENTS_DIR` (default `events`).
+
+use std::fmt::Display;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+use rust_emperor::game::army::Upkeep;
+use rust_emperor::game::balance::{self, Balance};
+use rust_emperor::game::battle::{self, BattleReport};
+use rust_emperor::game::buildings::{Building, FULL_CONDITION};
+use rust_emperor::game::error::GameError;
+use rust_emperor::game::report::RoundReport;
+use rust_emperor::game::{event_data, events};
+use rust_emperor::game::{rng, round, CustomRules, Difficulty, GameState, Gender};
+
+/// Line-based input. Prompts return `None` when the player cancels with a blank line
+/// or when stdin is closed; `closed` tells the two apart.
+struct Console {
+    input: io::StdinLock<'static>,
+    closed: bool,
+}
+
+impl Console {
+    fn new() -> Self {
+        Self {
+            input: io::stdin().lock(),
+            closed: false,
+        }
+    }
+
+    fn prompt(&mut self, text: &str) -> Option<String> {
+        print!("{} ", text);
+        io::stdout().flush().ok()?;
+        let mut line = String::new();
+        match self.input.read_line(&mut line) {
+            Ok(0) | Err(_) => {
+                self.closed = true;
+                None
+            }
+            Ok(_) => Some(line.trim().to_string()),
+        }
+    }
+
+    /// Asks until the answer parses; a blank answer cancels
+    fn ask_number<T: FromStr>(&mut self, text: &str) -> Option<T> {
+        loop {
+            let answer = self.prompt(text)?;
+            if answer.is_empty() {
+                return None;
+            }
+            match answer.parse() {
+                Ok(value) => return Some(value),
+                Err(_) => println!("Please enter a number."),
+            }
+        }
+    }
+
+    /// Like `choose`, but a blank line asks again; only a closed stdin gives up
+    fn choose_required(&mut self, title: &str, options: &[&str]) -> Option<usize> {
+        loop {
+            if let Some(choice) = self.choose(title, options) {
+                return Some(choice);
+            }
+            if self.closed {
+                return None;
+            }
+        }
+    }
+
+    /// Shows numbered options and returns the index of the chosen one
+    fn choose(&mut self, title: &str, options: &[&str]) -> Option<usize> {
+        println!();
+        println!("{}", title);
+        for (index, option) in options.iter().enumerate() {
+            println!("  {}) {}", index + 1, option);
+        }
+        loop {
+            let choice: usize = self.ask_number(">")?;
+            if (1..=options.len()).contains(&choice) {
+                return Some(choice - 1);
+            }
+            println!("Choose 1-{}.", options.len());
+        }
+    }
+}
+
+fn main() {
+    if let Ok(path) = std::env::var("EMPEROR_BALANCE") {
+        match Balance::load(&path) {
+            Ok(loaded) => {
+                balance::install(loaded);
+            }
+            Err(err) => {
+                eprintln!("{}: {}", path, err);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let events_dir = std::env::var("EMPEROR_EVENTS_DIR").unwrap_or_else(|_| "events".to_string());
+    match event_data::load_registry(&events_dir) {
+        Ok(registry) => {
+            events::install(registry);
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    }
+
+    let mut console = Console::new();
+
+    println!("=== Dark Emperor ===");
+    let Some(mut game) = new_game(&mut console) else {
+        return;
+    };
+
+    while play_turn(&mut console, &mut game) {
+        if game.is_game_ended {
+            print_game_over(&game);
+            print_chronicle(&game);
+            return;
+        }
+    }
+    println!();
+    println!("Farewell, {} {}.", game.get_grade_title(), game.user_name);
+}
+
+fn new_game(console: &mut Console) -> Option<GameState> {
+    let mut name = String::new();
+    while name.is_empty() {
+        name = console.prompt("Your name:")?;
+    }
+
+    let gender = match console.choose_required("Gender", &["Female", "Male"])? {
+        0 => Gender::Female,
+        _ => Gender::Male,
+    };
+    let difficulty =
+        match console.choose_required("Difficulty", &["Easy", "Medium", "Hard", "Custom"])? {
+            0 => Difficulty::Easy,
+            1 => Difficulty::Medium,
+            2 => Difficulty::Hard,
+            _ => Difficulty::Custom,
+        };
+    let rules = if difficulty == Difficulty::Custom {
+        Some(custom_rules(console)?)
+    } else {
+        None
+    };
+    let seed = console
+        .prompt("Seed (blank for random):")
+        .and_then(|text| rng::parse_seed(&text))
+        .unwrap_or_else(rng::random_seed);
+
+    Some(match rules {
+        Some(rules) => GameState::with_rules(name, gender, rules, seed),
+        None => GameState::with_seed(name, gender, difficulty, seed),
+    })
+}
+
+/// Asks for each custom rule until they are all valid; blank answers keep the default
+fn custom_rules(console: &mut Console) -> Option<CustomRules> {
+    loop {
+        let mut rules = CustomRules::default();
+        println!("Custom rules (blank keeps the value in brackets)");
+        rules.gold = ask_rule(console, "Starting gold", rules.gold)?;
+        rules.food = ask_rule(console, "Starting food", rules.food)?;
+        rules.citizens = ask_rule(console, "Starting citizens", rules.citizens)?;
+        rules.farms = ask_rule(console, "Starting farms", rules.farms)?;
+        rules.start_year = ask_rule(console, "Start year", rules.start_year)?;
+        rules.end_year = ask_rule(console, "End year", rules.end_year)?;
+        rules.event_percent = ask_rule(console, "Event chance in %", rules.event_percent)?;
+        rules.trade_limit = ask_rule(console, "Trade limit", rules.trade_limit)?;
+        rules.year_limit =
+            console.choose_required("Does the reign end after the end year?", &["Yes", "No"])? == 0;
+
+        match rules.validate() {
+            Ok(()) => return Some(rules),
+            Err(err) => println!("✗ {}", err),
+        }
+    }
+}
+
+fn ask_rule<T: FromStr + Display + Copy>(
+    console: &mut Console,
+    name: &str,
+    default: T,
+) -> Option<T> {
+    let value = console.ask_number(&format!("{} [{}]:", name, default));
+    if console.closed {
+        return None;
+    }
+    Some(value.unwrap_or(default))
+}
+
+/// One pass through the main menu. Returns `false` when the player quits.
+fn play_turn(console: &mut Console, game: &mut GameState) -> bool {
+    print_status(game);
+
+    let Some(choice) = console.choose(
+        "What is your command?",
+        &[
+            "Set taxes",
+            "Set food supply",
+            "Build",
+            "Upgrade castle",
+            "Trade",
+            "Army",
+            "Grade requirements",
+            "Chronicle",
+            "Finish round",
+            "Quit",
+        ],
+    ) else {
+        return !console.closed;
+    };
+
+    match choice {
+        0 => {
+            if let Some(level) = console.ask_number::<u8>("Tax level (0-5):") {
+                game.taxes_level = level.min(5);
+            }
+        }
+        1 => {
+            if let Some(level) = console.ask_number::<u8>("Food supply (0-5):") {
+                game.food_supply = level.min(5);
+            }
+        }
+        2 => build_menu(console, game),
+        3 => report_action(
+            game.upgrade_castle(),
+            format!("Castle upgraded to level {}.", game.castle_level),
+        ),
+        4 => trade_menu(console, game),
+        5 => army_menu(console, game),
+        6 => print_grade_requirements(game),
+        7 => print_chronicle(game),
+        8 => {
+            let report = round::play_seeded_round(game);
+            print_report(&report, game);
+            decide_event(console, game);
+        }
+        _ => return false,
+    }
+    !console.closed
+}
+
+fn build_menu(console: &mut Console, game: &mut GameState) {
+    enum Order {
+        Build(Building),
+        Repair(Building),
+        Demolish(Building),
+    }
+
+    // Every kind can be built; worn kinds can be repaired and existing ones demolished
+    let mut orders: Vec<(String, Order)> = Building::ALL
+        .into_iter()
+        .map(|building| {
+            let label = format!(
+                "Build a {} ({} gold)",
+                building,
+                game.building_price(building)
+            );
+            (label, Order::Build(building))
+        })
+        .collect();
+    for building in Building::ALL {
+        if game.building_quantity(building) > 0 && game.condition(building) < FULL_CONDITION {
+            let label = format!(
+                "Repair the {} at {}% ({} gold)",
+                building.plural(),
+                game.condition(building),
+                game.repair_cost(building)
+            );
+            orders.push((label, Order::Repair(building)));
+        }
+    }
+    for building in Building::ALL {
+        if game.building_quantity(building) > 0 {
+            let label = format!(
+                "Demolish a {} (+{} gold)",
+                building,
+                game.demolish_refund(building)
+            );
+            orders.push((label, Order::Demolish(building)));
+        }
+    }
+    let options: Vec<&str> = orders.iter().map(|(label, _)| label.as_str()).collect();
+
+    let Some(choice) = console.choose("Build", &options) else {
+        return;
+    };
+    match orders[choice].1 {
+        Order::Build(building) => {
+            let result = match building {
+                Building::Farm => game.build_farm(),
+                Building::Mine => game.build_mine(),
+                Building::Smithy => game.build_smithy(),
+                Building::Market => game.build_market(),
+            };
+            report_action(result, format!("Built a new {}.", building));
+        }
+        Order::Repair(building) => {
+            let result = game.repair(building);
+            report_action(result, format!("Repaired the {}.", building.plural()));
+        }
+        Order::Demolish(building) => {
+            let result = match building {
+                Building::Farm => game.demolish_farm(),
+                Building::Mine => game.demolish_mine(),
+                Building::Smithy => game.demolish_smithy(),
+                Building::Market => game.demolish_market(),
+            };
+            report_action(result, format!("Demolished a {}.", building));
+        }
+    }
+}
+
+fn trade_menu(console: &mut Console, game: &mut GameState) {
+    let options = [
+        format!("Buy food ({} gold per 100)", game.price_for_food),
+        format!("Sell food ({} gold per 100)", game.price_for_food),
+        format!("Buy iron ({} gold each)", game.price_for_iron),
+        format!("Sell iron ({} gold each)", game.price_for_iron),
+        format!("Buy weapons ({} gold each)", game.price_for_weapon),
+        format!("Sell weapons ({} gold each)", game.price_for_weapon),
+        format!("Buy armor ({} gold each)", game.price_for_armor),
+        format!("Sell armor ({} gold each)", game.price_for_armor),
+    ];
+    let options: Vec<&str> = options.iter().map(String::as_str).collect();
+
+    let Some(choice) = console.choose("Trade", &options) else {
+        return;
+    };
+    if choice < 2 {
+        let Some(quantity) = console.ask_number::<i32>("Quantity:") else {
+            return;
+        };
+        let result = if choice == 0 {
+            game.buy_food(quantity)
+        } else {
+            game.sell_food(quantity)
+        };
+        report_action(result, format!("{} done.", options[choice]));
+    } else {
+        let Some(quantity) = console.ask_number::<i16>("Quantity:") else {
+            return;
+        };
+        let result = match choice {
+            2 => game.buy_iron(quantity),
+            3 => game.sell_iron(quantity),
+            4 => game.buy_weapons(quantity),
+            5 => game.sell_weapons(quantity),
+            6 => game.buy_armor(quantity),
+            _ => game.sell_armor(quantity),
+        };
+        report_action(result, format!("{} done.", options[choice]));
+    }
+}
+
+fn army_menu(console: &mut Console, game: &mut GameState) {
+    let recruit = format!(
+        "Recruit soldiers ({} gold and 1 weapon each)",
+        game.soldier_price
+    );
+    let recruit_armored = format!(
+        "Recruit armored soldiers ({} gold, 1 weapon and 1 armor each)",
+        game.soldier_price
+    );
+    let Some(choice) = console.choose(
+        "Army",
+        &[
+            recruit.as_str(),
+            recruit_armored.as_str(),
+            "Discharge soldiers",
+            "Raid an outpost of the Dark Emperor",
+        ],
+    ) else {
+        return;
+    };
+    if choice == 3 {
+        match battle::raid(game) {
+            Ok(report) => print_battle(&report),
+            Err(err) => println!("✗ {}", err),
+        }
+        return;
+    }
+    let Some(quantity) = console.ask_number::<i16>("How many soldiers:") else {
+        return;
+    };
+
+    if choice == 0 {
+        report_action(
+            game.recruit_soldiers(quantity, false),
+            format!("Recruited {} soldiers.", quantity),
+        );
+    } else if choice == 1 {
+        report_action(
+            game.recruit_soldiers(quantity, true),
+            format!("Recruited {} armored soldiers.", quantity),
+        );
+    } else {
+        report_action(
+            game.discharge_soldiers(quantity),
+            format!("Discharged {} soldiers.", quantity),
+        );
+    }
+}
+
+/// Asks how to answer a decision event from the round just played
+fn decide_event(console: &mut Console, game: &mut GameState) {
+    let Some(ref event) = game.pending_event else {
+        return;
+    };
+    let labels: Vec<&str> = event.choices.iter().map(|c| c.label.as_str()).collect();
+    let Some(choice) = console.choose_required("What will you do?", &labels) else {
+        return;
+    };
+    let outcome = event.choices[choice].outcome.clone();
+    report_action(round::resolve_event(game, choice), outcome);
+    if let Some(battle) = game.last_report.as_ref().and_then(|r| r.battle.as_ref()) {
+        print_battle(battle);
+    }
+}
+
+fn report_action(result: Result<(), GameError>, success: impl Display) {
+    match result {
+        Ok(()) => println!("✓ {}", success),
+        Err(err) => println!("✗ {}", err),
+    }
+}
+
+fn print_status(game: &GameState) {
+    println!();
+    println!(
+        "--- {} {} | Year {} | Round {} | Seed {} ---",
+        game.get_grade_title(),
+        game.user_name,
+        game.year,
+        game.round,
+        game.seed
+    );
+    println!(
+        "Gold {:>7}   Food {:>7}   Citizens {:>6}   Popularity {:>3}%",
+        game.gold, game.food_quantity, game.man_quantity, game.popularity_percent
+    );
+    println!(
+        "Iron {:>7}   Weapons {:>4}   Armor {:>4}   Soldiers {:>6} ({} armored)",
+        game.iron_quantity,
+        game.weapon_quantity,
+        game.armor_quantity,
+        game.soldier_quantity,
+        game.armored_soldier_quantity
+    );
+    if game.soldier_quantity > 0 {
+        let upkeep = Upkeep::owed(game.soldier_quantity);
+        println!(
+            "Morale {} ({})   Upkeep {} gold and {} food a year",
+            game.morale,
+            game.morale_label(),
+            upkeep.wages,
+            upkeep.rations
+        );
+    }
+    println!(
+        "Castle {}   Farms {}   Mines {}   Smithies {}   Markets {}",
+        game.castle_level,
+        game.farm_quantity,
+        game.mine_quantity,
+        game.smithy_quantity,
+        game.market_quantity
+    );
+    let upkeep = game.building_upkeep();
+    if upkeep > 0 {
+        let condition: Vec<String> = Building::ALL
+            .into_iter()
+            .filter(|&building| game.building_quantity(building) > 0)
+            .map(|building| format!("{} {}%", building.plural(), game.condition(building)))
+            .collect();
+        println!(
+            "Condition {}   Upkeep {} gold a year",
+            condition.join(", "),
+            upkeep
+        );
+    }
+    println!(
+        "Taxes {}/5   Food supply {}/5",
+        game.taxes_level, game.food_supply
+    );
+}
+
+fn print_grade_requirements(game: &GameState) {
+    println!();
+    println!("To become {}:", game.get_next_grade_title());
+    for (name, current, required) in game.get_grade_requirements() {
+        let mark = if current >= required { "✓" } else { " " };
+        println!("  [{}] {:<12} {:>7} / {}", mark, name, current, required);
+    }
+}
+
+fn print_report(report: &RoundReport, game: &GameState) {
+    println!();
+    println!("=== Round Report - Year {} ===", report.year);
+    for step in &report.steps {
+        let changes: Vec<String> = step
+            .changes()
+            .iter()
+            .map(|change| {
+                format!(
+                    "{} {} → {} ({:+})",
+                    change.label,
+                    change.before,
+                    change.after,
+                    change.delta()
+                )
+            })
+            .collect();
+        if step.detail.is_empty() {
+            println!("{}: {}", step.label, changes.join(", "));
+        } else {
+            println!("{} ({}): {}", step.label, step.detail, changes.join(", "));
+        }
+    }
+
+    if report.maintenance.decay > 0 {
+        println!(
+            "The treasury could not pay {} gold of building upkeep: the buildings lost {}% condition.",
+            report.maintenance.upkeep - report.maintenance.paid,
+            report.maintenance.decay
+        );
+    }
+
+    if let Some(ref event) = report.event {
+        println!();
+        println!("Event: {}", event.title);
+        println!("{}", event.description);
+    }
+    if let Some(ref battle) = report.battle {
+        println!(
+            "Battle: {}, your army {} against {}",
+            battle.outcome(),
+            battle.army_roll,
+            battle.enemy_roll
+        );
+    }
+
+    if report.promoted {
+        println!();
+        println!(
+            "*** Congratulations! You have been promoted to {}! ***",
+            game.get_grade_title()
+        );
+    }
+}
+
+fn print_battle(battle: &BattleReport) {
+    println!();
+    println!(
+        "=== {} - Year {}: {} ===",
+        battle.kind,
+        battle.year,
+        battle.title()
+    );
+    println!(
+        "Your army {} (on the day {}) against {} (on the day {}): {}",
+        battle.army.total,
+        battle.army_roll,
+        battle.enemy,
+        battle.enemy_roll,
+        battle.outcome()
+    );
+    for line in &battle.lines {
+        println!("{}", line);
+    }
+}
+
+fn print_chronicle(game: &GameState) {
+    println!();
+    println!("=== Chronicle ===");
+    for entry in &game.chronicle {
+        println!("{}  {}: {}", entry.year, entry.title, entry.text);
+        if !entry.effects.is_empty() {
+            println!("      {}", entry.effects.join(", "));
+        }
+    }
+}
+
+fn print_game_over(game: &GameState) {
+    println!();
+    println!("=== Game Over ===");
+    if game.is_won {
+        println!(
+            "Long live {} {}! You united the realm in {}.",
+            game.get_grade_title(),
+            game.user_name,
+            game.year
+        );
+        println!("Score: {}", game.calculate_score());
+    } else {
+        println!(
+            "The reign of {} {} has ended without an empire.",
+            game.get_grade_title(),
+            game.user_name
+        );
+    }
+}
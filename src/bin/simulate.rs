@@ -0,0 +1,359 @@
+//! Balance simulator
+//! Plays thousands of seeded games per difficulty and strategy and prints win rates,
+//! score spread, bankruptcy/starvation frequency and how fast each grade is reached.
+//!
+//! `cargo run --release --bin simulate -- --games 1000 --format table`
+
+use std::process::ExitCode;
+use std::thread;
+
+use rust_emperor::game::advisor::StrategyKind;
+use rust_emperor::game::balance::{self, Balance};
+use rust_emperor::game::event_data;
+use rust_emperor::game::events;
+use rust_emperor::game::simulation::{self, Summary, GRADE_COUNT};
+use rust_emperor::game::Difficulty;
+
+const USAGE: &str = "\
+Usage: simulate [options]
+
+Options:
+  --games <n>          Games per difficulty and strategy (default 1000)
+  --seed <n>           First seed; games use consecutive seeds (default 0)
+  --difficulty <list>  easy, medium, hard or all, comma separated (default all)
+  --strategy <list>    greedy, balanced, rush or all, comma separated (default all)
+  --format <format>    table, csv or json (default table)
+  --balance <file>     Balance file to play with instead of the built-in values
+  --events <dir>       Event files to add to the built-in events
+                       (default $EMPEROR_EVENTS_DIR or events)
+  --help               Show this message";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Table,
+    Csv,
+    Json,
+}
+
+#[derive(Debug)]
+struct Options {
+    games: u32,
+    seed: u64,
+    difficulties: Vec<Difficulty>,
+    strategies: Vec<StrategyKind>,
+    format: Format,
+    balance: Option<String>,
+    events: Option<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            games: 1000,
+            seed: 0,
+            difficulties: vec![Difficulty::Easy, Difficulty::Medium, Difficulty::Hard],
+            strategies: StrategyKind::ALL.to_vec(),
+            format: Format::Table,
+            balance: None,
+            events: None,
+        }
+    }
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut options = Options::default();
+    let mut args = args;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--help" | "-h" => return Ok(None),
+            "--games" | "--seed" | "--difficulty" | "--strategy" | "--format" | "--balance"
+            | "--events" => {}
+            _ => return Err(format!("Unknown option: {}", arg)),
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {}", arg))?;
+        match arg.as_str() {
+            "--games" => {
+                options.games = value
+                    .parse()
+                    .map_err(|_| format!("Invalid number of games: {}", value))?
+            }
+            "--seed" => {
+                options.seed = value
+                    .parse()
+                    .map_err(|_| format!("Invalid seed: {}", value))?
+            }
+            "--difficulty" => options.difficulties = parse_list(&value, parse_difficulty)?,
+            "--strategy" => options.strategies = parse_list(&value, parse_strategy)?,
+            "--format" => {
+                options.format = match value.as_str() {
+                    "table" => Format::Table,
+                    "csv" => Format::Csv,
+                    "json" => Format::Json,
+                    _ => return Err(format!("Unknown format: {}", value)),
+                }
+            }
+            "--balance" => options.balance = Some(value),
+            "--events" => options.events = Some(value),
+            _ => unreachable!(),
+        }
+    }
+    Ok(Some(options))
+}
+
+/// Parses a comma-separated list where `all` expands to every value
+fn parse_list<T>(value: &str, parse: fn(&str) -> Result<Vec<T>, String>) -> Result<Vec<T>, String> {
+    let mut items = Vec::new();
+    for part in value.split(',') {
+        items.extend(parse(part.trim())?);
+    }
+    Ok(items)
+}
+
+fn parse_difficulty(value: &str) -> Result<Vec<Difficulty>, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "easy" => Ok(vec![Difficulty::Easy]),
+        "medium" => Ok(vec![Difficulty::Medium]),
+        "hard" => Ok(vec![Difficulty::Hard]),
+        "all" => Ok(vec![Difficulty::Easy, Difficulty::Medium, Difficulty::Hard]),
+        _ => Err(format!("Unknown difficulty: {}", value)),
+    }
+}
+
+fn parse_strategy(value: &str) -> Result<Vec<StrategyKind>, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "greedy" => Ok(vec![StrategyKind::Greedy]),
+        "balanced" => Ok(vec![StrategyKind::Balanced]),
+        "rush" => Ok(vec![StrategyKind::Rush]),
+        "all" => Ok(StrategyKind::ALL.to_vec()),
+        _ => Err(format!("Unknown strategy: {}", value)),
+    }
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Some(ref path) = options.balance {
+        match Balance::load(path) {
+            Ok(loaded) => {
+                balance::install(loaded);
+            }
+            Err(err) => {
+                eprintln!("{}: {}", path, err);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let events_dir = options.events.clone().unwrap_or_else(|| {
+        std::env::var("EMPEROR_EVENTS_DIR").unwrap_or_else(|_| "events".to_string())
+    });
+    match event_data::load_registry(&events_dir) {
+        Ok(registry) => {
+            events::install(registry);
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    // Every strategy/difficulty pair runs on its own thread
+    let summaries: Vec<Summary> = thread::scope(|scope| {
+        let handles: Vec<_> = options
+            .strategies
+            .iter()
+            .flat_map(|&kind| options.difficulties.iter().map(move |&d| (kind, d)))
+            .map(|(kind, difficulty)| {
+                let (seed, games) = (options.seed, options.games);
+                scope.spawn(move || {
+                    let outcomes = simulation::simulate(kind.strategy(), difficulty, seed, games);
+                    Summary::of(kind, difficulty, &outcomes)
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("simulation thread panicked"))
+            .collect()
+    });
+
+    match options.format {
+        Format::Table => print_table(&summaries),
+        Format::Csv => print_csv(&summaries),
+        Format::Json => match serde_json::to_string_pretty(&summaries) {
+            Ok(json) => println!("{}", json),
+            Err(err) => {
+                eprintln!("Could not serialize results: {}", err);
+                return ExitCode::FAILURE;
+            }
+        },
+    }
+    ExitCode::SUCCESS
+}
+
+fn percent(rate: f64) -> String {
+    format!("{:.1}%", rate * 100.0)
+}
+
+fn print_table(summaries: &[Summary]) {
+    println!(
+        "{:<16} {:<10} {:>6} {:>7} {:>9} {:>8} {:>19} {:>9} {:>9}",
+        "Strategy",
+        "Difficulty",
+        "Games",
+        "Win",
+        "Mean year",
+        "Score",
+        "Score p25/p50/p75",
+        "Bankrupt",
+        "Starved"
+    );
+    for summary in summaries {
+        let spread = format!(
+            "{}/{}/{}",
+            summary.scores.p25, summary.scores.median, summary.scores.p75
+        );
+        println!(
+            "{:<16} {:<10} {:>6} {:>7} {:>9.1} {:>8.0} {:>19} {:>9} {:>9}",
+            summary.strategy.to_string(),
+            summary.difficulty.to_string(),
+            summary.games,
+            percent(summary.win_rate),
+            summary.mean_final_year,
+            summary.scores.mean,
+            spread,
+            percent(summary.bankruptcy_rate),
+            percent(summary.starvation_rate)
+        );
+    }
+
+    println!();
+    println!("Rounds to reach each grade (share of games that reached it)");
+    print!("{:<16} {:<10}", "Strategy", "Difficulty");
+    for grade in 1..=GRADE_COUNT {
+        print!(" {:>15}", format!("Grade {}", grade));
+    }
+    println!();
+    for summary in summaries {
+        print!(
+            "{:<16} {:<10}",
+            summary.strategy.to_string(),
+            summary.difficulty.to_string()
+        );
+        for timing in &summary.grades {
+            let cell = match timing.mean_rounds {
+                Some(rounds) => format!("{:.1} ({})", rounds, percent(timing.reached_rate)),
+                None => "-".to_string(),
+            };
+            print!(" {:>15}", cell);
+        }
+        println!();
+    }
+}
+
+fn print_csv(summaries: &[Summary]) {
+    let mut header = vec![
+        "strategy",
+        "difficulty",
+        "games",
+        "win_rate",
+        "mean_final_year",
+        "score_min",
+        "score_p25",
+        "score_median",
+        "score_p75",
+        "score_max",
+        "score_mean",
+        "bankruptcy_rate",
+        "starvation_rate",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect::<Vec<_>>();
+    for grade in 1..=GRADE_COUNT {
+        header.push(format!("grade{}_rate", grade));
+        header.push(format!("grade{}_mean_rounds", grade));
+    }
+    println!("{}", header.join(","));
+
+    for summary in summaries {
+        let mut row = vec![
+            format!("{:?}", summary.strategy),
+            summary.difficulty.to_string(),
+            summary.games.to_string(),
+            format!("{:.4}", summary.win_rate),
+            format!("{:.2}", summary.mean_final_year),
+            summary.scores.min.to_string(),
+            summary.scores.p25.to_string(),
+            summary.scores.median.to_string(),
+            summary.scores.p75.to_string(),
+            summary.scores.max.to_string(),
+            format!("{:.2}", summary.scores.mean),
+            format!("{:.4}", summary.bankruptcy_rate),
+            format!("{:.4}", summary.starvation_rate),
+        ];
+        for timing in &summary.grades {
+            row.push(format!("{:.4}", timing.reached_rate));
+            row.push(
+                timing
+                    .mean_rounds
+                    .map(|rounds| format!("{:.2}", rounds))
+                    .unwrap_or_default(),
+            );
+        }
+        println!("{}", row.join(","));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> impl Iterator<Item = String> {
+        list.iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = parse_args(args(&[
+            "--games",
+            "50",
+            "--difficulty",
+            "easy,hard",
+            "--strategy",
+            "rush",
+            "--format",
+            "csv",
+        ]))
+        .unwrap()
+        .unwrap();
+        assert_eq!(options.games, 50);
+        assert_eq!(
+            options.difficulties,
+            vec![Difficulty::Easy, Difficulty::Hard]
+        );
+        assert_eq!(options.strategies, vec![StrategyKind::Rush]);
+        assert_eq!(options.format, Format::Csv);
+
+        assert!(parse_args(args(&["--games"])).is_err());
+        assert!(parse_args(args(&["--format", "xml"])).is_err());
+        assert!(parse_args(args(&["--bogus"])).is_err());
+        assert!(parse_args(args(&["--help"])).unwrap().is_none());
+    }
+}
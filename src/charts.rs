@@ -0,0 +1,198 @@
+//! Statistics charts
+//! Line charts of the round history, laid out here and drawn as inline SVG by
+//! `line_chart.html`, so they need no JavaScript.
+
+use crate::game::history::RoundRecord;
+
+const WIDTH: i32 = 320;
+const HEIGHT: i32 = 160;
+const LEFT: i32 = 48;
+const RIGHT: i32 = 8;
+const TOP: i32 = 8;
+const BOTTOM: i32 = 20;
+
+/// A line's name, color and how to read its value from a record
+type Line = (&'static str, &'static str, fn(&RoundRecord) -> i64);
+
+/// One line of a chart
+pub struct Series {
+    pub name: &'static str,
+    pub color: &'static str,
+    /// SVG `points` of the polyline
+    pub points: String,
+    pub last: i64,
+}
+
+/// A chart with its axes already scaled to the data
+pub struct LineChart {
+    pub title: &'static str,
+    pub series: Vec<Series>,
+    pub min: i64,
+    pub max: i64,
+    pub first_year: u16,
+    pub last_year: u16,
+}
+
+impl LineChart {
+    fn new(title: &'static str, history: &[RoundRecord], lines: &[Line]) -> Self {
+        let values = || {
+            lines
+                .iter()
+                .flat_map(|(_, _, value)| history.iter().map(value))
+        };
+        let min = values().min().unwrap_or(0).min(0);
+        let max = values().max().unwrap_or(0).max(min + 1);
+        let steps = (history.len() as i64 - 1).max(1);
+
+        let series = lines
+            .iter()
+            .map(|&(name, color, value)| {
+                let points = history
+                    .iter()
+                    .enumerate()
+                    .map(|(index, record)| {
+                        let x = LEFT as i64 + index as i64 * Self::plot_width() / steps;
+                        let y =
+                            TOP as i64 + (max - value(record)) * Self::plot_height() / (max - min);
+                        format!("{},{}", x, y)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                Series {
+                    name,
+                    color,
+                    points,
+                    last: history.last().map_or(0, value),
+                }
+            })
+            .collect();
+
+        Self {
+            title,
+            series,
+            min,
+            max,
+            first_year: history.first().map_or(0, |record| record.year),
+            last_year: history.last().map_or(0, |record| record.year),
+        }
+    }
+
+    fn plot_width() -> i64 {
+        (WIDTH - LEFT - RIGHT) as i64
+    }
+
+    fn plot_height() -> i64 {
+        (HEIGHT - TOP - BOTTOM) as i64
+    }
+
+    // Layout values for the template
+    pub fn width(&self) -> i32 {
+        WIDTH
+    }
+
+    pub fn height(&self) -> i32 {
+        HEIGHT
+    }
+
+    pub fn left(&self) -> i32 {
+        LEFT
+    }
+
+    pub fn right(&self) -> i32 {
+        WIDTH - RIGHT
+    }
+
+    pub fn top(&self) -> i32 {
+        TOP
+    }
+
+    pub fn bottom(&self) -> i32 {
+        HEIGHT - BOTTOM
+    }
+
+    pub fn label_y(&self) -> i32 {
+        HEIGHT - 6
+    }
+}
+
+/// The statistics panel's charts, or none before the first round is played
+pub fn statistics(history: &[RoundRecord]) -> Vec<LineChart> {
+    if history.len() < 2 {
+        return Vec::new();
+    }
+    vec![
+        LineChart::new(
+            "Treasury",
+            history,
+            &[("Gold", "#b8860b", |r| r.gold as i64)],
+        ),
+        LineChart::new(
+            "Food stores",
+            history,
+            &[("Food", "#7a9b6d", |r| r.food as i64)],
+        ),
+        LineChart::new(
+            "People",
+            history,
+            &[
+                ("Citizens", "#5b7fa6", |r| r.citizens as i64),
+                ("Soldiers", "#9b3d2e", |r| r.soldiers as i64),
+            ],
+        ),
+        LineChart::new(
+            "Popularity and morale",
+            history,
+            &[
+                ("Popularity %", "#a0522d", |r| r.popularity as i64),
+                ("Army morale", "#9b3d2e", |r| r.morale as i64),
+            ],
+        ),
+        LineChart::new(
+            "Prices",
+            history,
+            &[
+                ("Food", "#7a9b6d", |r| r.food_price as i64),
+                ("Iron", "#6b6b6b", |r| r.iron_price as i64),
+                ("Weapons", "#9b3d2e", |r| r.weapon_price as i64),
+                ("Armor", "#5b7fa6", |r| r.armor_price as i64),
+            ],
+        ),
+        LineChart::new(
+            "Buildings",
+            history,
+            &[
+                ("Farms", "#7a9b6d", |r| r.farms as i64),
+                ("Mines", "#6b6b6b", |r| r.mines as i64),
+                ("Smithies", "#9b3d2e", |r| r.smithies as i64),
+                ("Markets", "#b8860b", |r| r.markets as i64),
+                ("Castle", "#5b7fa6", |r| r.castle_level as i64),
+            ],
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(year: u16, gold: i32) -> RoundRecord {
+        RoundRecord {
+            year,
+            gold,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_chart_scaling() {
+        assert!(statistics(&[record(1440, 100)]).is_empty());
+
+        let history = [record(1440, 0), record(1441, 50), record(1442, 100)];
+        let charts = statistics(&history);
+        let gold = &charts[0].series[0];
+        assert_eq!(charts[0].max, 100);
+        assert_eq!(gold.points, "48,140 180,74 312,8");
+        assert_eq!(gold.last, 100);
+        assert_eq!((charts[0].first_year, charts[0].last_year), (1440, 1442));
+    }
+}
@@ -1,19 +1,17 @@
-/// Custom filters for Askama templates
-/// Provides number formatting and other utility functions
+//! Custom filters for Askama templates
+//! Provides number formatting and other utility functions
 
 /// Format a number with thousand separators
 /// Example: 1000 -> "1,000"
 pub fn format_number(num: &i32) -> askama::Result<String> {
     let num_str = num.to_string();
     let mut result = String::new();
-    let mut count = 0;
 
-    for c in num_str.chars().rev() {
+    for (count, c) in num_str.chars().rev().enumerate() {
         if count > 0 && count % 3 == 0 {
             result.push(',');
         }
         result.push(c);
-        count += 1;
     }
 
     Ok(result.chars().rev().collect())
@@ -23,14 +21,12 @@ pub fn format_number(num: &i32) -> askama::Result<String> {
 pub fn format_number_i64(num: &i64) -> askama::Result<String> {
     let num_str = num.to_string();
     let mut result = String::new();
-    let mut count = 0;
 
-    for c in num_str.chars().rev() {
+    for (count, c) in num_str.chars().rev().enumerate() {
         if count > 0 && count % 3 == 0 {
             result.push(',');
         }
         result.push(c);
-        count += 1;
     }
 
     Ok(result.chars().rev().collect())
@@ -40,19 +36,50 @@ pub fn format_number_i64(num: &i64) -> askama::Result<String> {
 pub fn format_number_usize(num: &usize) -> askama::Result<String> {
     let num_str = num.to_string();
     let mut result = String::new();
-    let mut count = 0;
 
-    for c in num_str.chars().rev() {
+    for (count, c) in num_str.chars().rev().enumerate() {
         if count > 0 && count % 3 == 0 {
             result.push(',');
         }
         result.push(c);
-        count += 1;
     }
 
     Ok(result.chars().rev().collect())
 }
 
+/// Format a unix timestamp (seconds) as a UTC date and time
+/// Example: 0 -> "1970-01-01 00:00"
+pub fn format_timestamp(secs: &u64) -> askama::Result<String> {
+    let days = (*secs / 86_400) as i64;
+    let time_of_day = *secs % 86_400;
+
+    // Civil-from-days conversion (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    Ok(format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        time_of_day / 3600,
+        time_of_day % 3600 / 60
+    ))
+}
+
+/// Make text safe for a Markdown table cell or heading
+/// Example: "A | B" -> "A \\| B"
+pub fn md_cell(text: &str) -> askama::Result<String> {
+    Ok(text.replace('|', "\\|").replace(['\n', '\r'], " "))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -79,4 +106,20 @@ mod tests {
         assert_eq!(format_number_usize(&1000).unwrap(), "1,000");
         assert_eq!(format_number_usize(&1000000).unwrap(), "1,000,000");
     }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(&0).unwrap(), "1970-01-01 00:00");
+        assert_eq!(format_timestamp(&951_827_696).unwrap(), "2000-02-29 12:34");
+        assert_eq!(
+            format_timestamp(&1_735_689_599).unwrap(),
+            "2024-12-31 23:59"
+        );
+    }
+
+    #[test]
+    fn test_md_cell() {
+        assert_eq!(md_cell("A | B").unwrap(), "A \\| B");
+        assert_eq!(md_cell("two\nlines").unwrap(), "two lines");
+    }
 }
@@ -0,0 +1,475 @@
+//! Computer advisors
+//! A `Strategy` looks at the kingdom and plans the player's actions for one turn.
+//! The same plans drive the "Advisor suggests" panel and autoplay.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::game::buildings::{Building, FULL_CONDITION};
+use crate::game::error::GameError;
+use crate::game::report::RoundReport;
+use crate::game::round;
+use crate::game::state::{GameState, IRON_TRADE_MARKETS, MIN_CITIZENS, WEAPON_TRADE_MARKETS};
+
+/// Average harvest of one farm (116 plus 12 times the mean roll of 0..64)
+const EXPECTED_HARVEST_PER_FARM: i32 = 494;
+
+/// Upper limit for one autoplay request
+pub const MAX_AUTOPLAY_ROUNDS: u16 = 100;
+
+/// One thing a player can do during a turn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    SetTaxes(u8),
+    SetFoodSupply(u8),
+    Build(Building),
+    Repair(Building),
+    UpgradeCastle,
+    BuyFood(i32),
+    SellFood(i32),
+    BuyIron(i16),
+    SellIron(i16),
+    BuyWeapons(i16),
+    SellWeapons(i16),
+    Recruit(i16),
+    Discharge(i16),
+}
+
+impl Action {
+    pub fn apply(self, game: &mut GameState) -> Result<(), GameError> {
+        match self {
+            Action::SetTaxes(level) => {
+                game.taxes_level = level.min(5);
+                Ok(())
+            }
+            Action::SetFoodSupply(level) => {
+                game.food_supply = level.min(5);
+                Ok(())
+            }
+            Action::Build(Building::Farm) => game.build_farm(),
+            Action::Build(Building::Mine) => game.build_mine(),
+            Action::Build(Building::Smithy) => game.build_smithy(),
+            Action::Build(Building::Market) => game.build_market(),
+            Action::Repair(building) => game.repair(building),
+            Action::UpgradeCastle => game.upgrade_castle(),
+            Action::BuyFood(quantity) => game.buy_food(quantity),
+            Action::SellFood(quantity) => game.sell_food(quantity),
+            Action::BuyIron(quantity) => game.buy_iron(quantity),
+            Action::SellIron(quantity) => game.sell_iron(quantity),
+            Action::BuyWeapons(quantity) => game.buy_weapons(quantity),
+            Action::SellWeapons(quantity) => game.sell_weapons(quantity),
+            Action::Recruit(quantity) => {
+                game.recruit_soldiers(quantity, game.armor_quantity >= quantity)
+            }
+            Action::Discharge(quantity) => game.discharge_soldiers(quantity),
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::SetTaxes(level) => write!(f, "Set taxes to {}", level),
+            Action::SetFoodSupply(level) => write!(f, "Set food rations to {}", level),
+            Action::Build(building) => write!(f, "Build a {}", building),
+            Action::Repair(building) => write!(f, "Repair the {}", building.plural()),
+            Action::UpgradeCastle => write!(f, "Upgrade the castle"),
+            Action::BuyFood(quantity) => write!(f, "Buy {} food", quantity),
+            Action::SellFood(quantity) => write!(f, "Sell {} food", quantity),
+            Action::BuyIron(quantity) => write!(f, "Buy {} iron", quantity),
+            Action::SellIron(quantity) => write!(f, "Sell {} iron", quantity),
+            Action::BuyWeapons(quantity) => write!(f, "Buy {} weapons", quantity),
+            Action::SellWeapons(quantity) => write!(f, "Sell {} weapons", quantity),
+            Action::Recruit(quantity) => write!(f, "Recruit {} soldiers", quantity),
+            Action::Discharge(quantity) => write!(f, "Discharge {} soldiers", quantity),
+        }
+    }
+}
+
+/// A computer ruler
+pub trait Strategy {
+    /// The actions to take this turn, in order
+    fn plan(&self, game: &GameState) -> Vec<Action>;
+}
+
+/// The built-in strategies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum StrategyKind {
+    /// Taxes high and reinvests everything in markets and farms
+    Greedy,
+    /// Keeps the people fed and content while meeting the next grade step by step
+    #[default]
+    Balanced,
+    /// Spends everything on whatever the next grade still lacks
+    Rush,
+}
+
+impl StrategyKind {
+    pub const ALL: [StrategyKind; 3] = [
+        StrategyKind::Greedy,
+        StrategyKind::Balanced,
+        StrategyKind::Rush,
+    ];
+
+    pub fn strategy(self) -> &'static dyn Strategy {
+        match self {
+            StrategyKind::Greedy => &GreedyEconomy,
+            StrategyKind::Balanced => &Balanced,
+            StrategyKind::Rush => &RushToGrade,
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            StrategyKind::Greedy => "High taxes, every coin reinvested in markets and farms.",
+            StrategyKind::Balanced => "Fed and content people, grade requirements met in turn.",
+            StrategyKind::Rush => "Everything spent on what the next grade still lacks.",
+        }
+    }
+}
+
+impl fmt::Display for StrategyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StrategyKind::Greedy => write!(f, "Greedy economy"),
+            StrategyKind::Balanced => write!(f, "Balanced"),
+            StrategyKind::Rush => write!(f, "Rush to grade"),
+        }
+    }
+}
+
+/// Plays `actions` on a scratch copy of the game so each decision sees the result of the
+/// previous ones; only actions that succeed end up in the plan
+struct Planner {
+    game: GameState,
+    actions: Vec<Action>,
+}
+
+impl Planner {
+    fn new(game: &GameState) -> Self {
+        Self {
+            game: game.clone(),
+            actions: Vec::new(),
+        }
+    }
+
+    fn try_action(&mut self, action: Action) -> bool {
+        let ok = action.apply(&mut self.game).is_ok();
+        if ok {
+            self.actions.push(action);
+        }
+        ok
+    }
+
+    fn set_taxes(&mut self, level: u8) {
+        if self.game.taxes_level != level {
+            self.try_action(Action::SetTaxes(level));
+        }
+    }
+
+    fn set_food_supply(&mut self, level: u8) {
+        if self.game.food_supply != level {
+            self.try_action(Action::SetFoodSupply(level));
+        }
+    }
+
+    /// Builds `building` while the treasury stays above `reserve`, at most `limit` times
+    fn build_while_above(&mut self, building: Building, reserve: i32, limit: usize) {
+        let price = self.game.building_price(building);
+        for _ in 0..limit {
+            if self.game.gold - price < reserve || !self.try_action(Action::Build(building)) {
+                break;
+            }
+        }
+    }
+
+    /// Repairs worn buildings while the treasury stays above `reserve`
+    fn repair_above(&mut self, reserve: i32) {
+        for building in Building::ALL {
+            if self.game.condition(building) < FULL_CONDITION
+                && self.game.gold - self.game.repair_cost(building) >= reserve
+            {
+                self.try_action(Action::Repair(building));
+            }
+        }
+    }
+
+    /// Food eaten per round at rations `level`
+    fn food_needed(&self, level: u8) -> i32 {
+        round::food_needed(self.game.man_quantity, level)
+    }
+
+    /// Makes sure there is food for this round at rations `level` and enough farms that the
+    /// next harvest covers it. Returns the rations level it could actually afford.
+    fn secure_food(&mut self, level: u8) -> u8 {
+        let needed = self.food_needed(level);
+        let harvest = self.game.farm_quantity as i32 * EXPECTED_HARVEST_PER_FARM;
+        let missing_farms =
+            (needed - harvest + EXPECTED_HARVEST_PER_FARM - 1) / EXPECTED_HARVEST_PER_FARM;
+        self.build_while_above(Building::Farm, 0, missing_farms.max(0) as usize);
+
+        let shortfall = needed - self.game.food_quantity;
+        if shortfall > 0 && self.game.market_quantity > 0 {
+            // Food is sold in lots of 100
+            let quantity = (shortfall + 99) / 100 * 100;
+            let affordable = self.game.gold / self.game.price_for_food.max(1) * 100;
+            self.try_action(Action::BuyFood(quantity.min(affordable)));
+        }
+
+        let mut level = level;
+        while level > 0 && self.food_needed(level) > self.game.food_quantity {
+            level -= 1;
+        }
+        level
+    }
+
+    /// Recruits up to `target` soldiers, making weapons available first where possible
+    fn recruit_up_to(&mut self, target: i16) {
+        let missing = target - self.game.soldier_quantity;
+        if missing <= 0 {
+            return;
+        }
+
+        let weapons_missing = missing - self.game.weapon_quantity;
+        if weapons_missing > 0 && self.game.market_quantity >= WEAPON_TRADE_MARKETS {
+            let affordable =
+                (self.game.gold / self.game.price_for_weapon.max(1)).min(i16::MAX as i32) as i16;
+            self.try_action(Action::BuyWeapons(weapons_missing.min(affordable)));
+        }
+
+        let spare_citizens =
+            self.game.man_quantity - MIN_CITIZENS - self.game.soldier_quantity as i32;
+        let affordable = self.game.gold / self.game.soldier_price.max(1);
+        let quantity = (missing as i32)
+            .min(self.game.weapon_quantity as i32)
+            .min(spare_citizens)
+            .min(affordable);
+        if quantity > 0 {
+            self.try_action(Action::Recruit(quantity as i16));
+        }
+    }
+
+    /// Sets up iron and weapon production when soldiers will be needed
+    fn build_arms_industry(&mut self, reserve: i32) {
+        if self.game.smithy_quantity == 0 {
+            self.build_while_above(Building::Smithy, reserve, 1);
+        }
+        if self.game.smithy_quantity > 0
+            && self.game.mine_quantity == 0
+            && self.game.market_quantity < IRON_TRADE_MARKETS
+        {
+            self.build_while_above(Building::Mine, reserve, 1);
+        }
+        if self.game.smithy_quantity > 0 && self.game.market_quantity >= IRON_TRADE_MARKETS {
+            let wanted = self.game.smithy_quantity * 8 - self.game.iron_quantity;
+            let affordable = (self.game.gold - reserve) / self.game.price_for_iron.max(1);
+            let quantity = (wanted as i32).min(affordable);
+            if quantity > 0 {
+                self.try_action(Action::BuyIron(quantity as i16));
+            }
+        }
+    }
+
+    /// What the next grade asks for, as (castle level, soldiers, gold, popularity)
+    fn next_grade_targets(&self) -> (i32, i16, i32, i32) {
+        let mut targets = (0, 0, 0, 0);
+        for (name, _, required) in self.game.get_grade_requirements() {
+            match name.as_str() {
+                "Castle Level" => targets.0 = required,
+                "Soldiers" => targets.1 = required as i16,
+                "Gold" => targets.2 = required,
+                "Popularity" => targets.3 = required,
+                _ => {}
+            }
+        }
+        targets
+    }
+
+    fn upgrade_castle_towards(&mut self, level: i32, reserve: i32) {
+        if (self.game.castle_level as i32) < level
+            && self.game.gold - self.game.price_for_castle >= reserve
+        {
+            self.try_action(Action::UpgradeCastle);
+        }
+    }
+}
+
+/// Taxes as high as popularity allows and reinvests everything in income
+pub struct GreedyEconomy;
+
+impl Strategy for GreedyEconomy {
+    fn plan(&self, game: &GameState) -> Vec<Action> {
+        let mut planner = Planner::new(game);
+        let (castle, soldiers, _, popularity) = planner.next_grade_targets();
+
+        // As high as popularity allows without falling below the next grade's requirement
+        let current = game.popularity_percent as i32;
+        let taxes = if current >= popularity + 10 {
+            4
+        } else if current >= popularity {
+            3
+        } else {
+            2
+        };
+        planner.set_taxes(taxes);
+        let wanted_rations = if current < popularity { 4 } else { 3 };
+        let rations = planner.secure_food(wanted_rations);
+        planner.set_food_supply(rations);
+
+        let reserve = planner.food_needed(rations) / 100 * game.price_for_food;
+        planner.repair_above(reserve);
+        planner.build_while_above(Building::Market, reserve, 5);
+
+        // Grades only get what is left over once income is flowing
+        let rich = reserve + 3 * game.price_for_castle;
+        planner.upgrade_castle_towards(castle, rich);
+        if soldiers > 0 && planner.game.gold > rich {
+            planner.build_arms_industry(rich);
+            planner.recruit_up_to(soldiers);
+        }
+
+        planner.actions
+    }
+}
+
+/// Keeps the people fed and content and works through the next grade's requirements
+pub struct Balanced;
+
+impl Strategy for Balanced {
+    fn plan(&self, game: &GameState) -> Vec<Action> {
+        let mut planner = Planner::new(game);
+        let (castle, soldiers, gold, popularity) = planner.next_grade_targets();
+
+        let taxes = if (game.popularity_percent as i32) < popularity {
+            2
+        } else {
+            3
+        };
+        planner.set_taxes(taxes);
+        let rations = planner.secure_food(4);
+        planner.set_food_supply(rations);
+
+        // Castle and army come first; gold for the grade is saved up afterwards
+        let reserve = planner.food_needed(4) / 100 * game.price_for_food;
+        planner.repair_above(reserve);
+        planner.upgrade_castle_towards(castle, reserve);
+        if soldiers > 0 {
+            planner.build_arms_industry(reserve);
+            planner.recruit_up_to(soldiers);
+        }
+        let savings = reserve.max(gold);
+        planner.build_while_above(Building::Market, savings + game.price_for_farm, 2);
+
+        planner.actions
+    }
+}
+
+/// Pours everything into whatever the next grade still lacks
+pub struct RushToGrade;
+
+impl Strategy for RushToGrade {
+    fn plan(&self, game: &GameState) -> Vec<Action> {
+        let mut planner = Planner::new(game);
+        let (castle, soldiers, gold, popularity) = planner.next_grade_targets();
+
+        // Popularity and population both grow fastest with low taxes and full rations;
+        // taxes only go up once popularity has a safe margin
+        let current = game.popularity_percent as i32;
+        let taxes = if current < popularity {
+            1
+        } else if current < popularity + 5 {
+            2
+        } else {
+            3
+        };
+        planner.set_taxes(taxes);
+        let rations = planner.secure_food(5);
+        planner.set_food_supply(rations);
+
+        planner.repair_above(0);
+        planner.upgrade_castle_towards(castle, 0);
+        if soldiers > game.soldier_quantity {
+            planner.build_arms_industry(0);
+            planner.recruit_up_to(soldiers);
+        }
+        if planner.game.market_quantity < 2 {
+            planner.build_while_above(Building::Market, gold, 1);
+        }
+
+        planner.actions
+    }
+}
+
+/// Applies the strategy's plan to `game`, returning each action with its outcome
+pub fn play_turn(
+    strategy: &dyn Strategy,
+    game: &mut GameState,
+) -> Vec<(Action, Result<(), GameError>)> {
+    strategy
+        .plan(game)
+        .into_iter()
+        .map(|action| (action, action.apply(game)))
+        .collect()
+}
+
+/// Lets the strategy play up to `rounds` rounds, stopping early when the game ends
+pub fn autoplay(strategy: &dyn Strategy, game: &mut GameState, rounds: u16) -> Vec<RoundReport> {
+    let mut reports = Vec::new();
+    for _ in 0..rounds.min(MAX_AUTOPLAY_ROUNDS) {
+        if game.is_game_ended {
+            break;
+        }
+        play_turn(strategy, game);
+        reports.push(round::play_seeded_round(game));
+    }
+    reports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Difficulty, Gender};
+
+    fn new_game(seed: u64) -> GameState {
+        GameState::with_seed("Bot".to_string(), Gender::Male, Difficulty::Medium, seed)
+    }
+
+    #[test]
+    fn test_plans_only_contain_valid_actions() {
+        for kind in StrategyKind::ALL {
+            let mut game = new_game(1);
+            for _ in 0..30 {
+                for (action, result) in play_turn(kind.strategy(), &mut game) {
+                    assert!(result.is_ok(), "{}: {} failed", kind, action);
+                }
+                round::play_seeded_round(&mut game);
+                if game.is_game_ended {
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_autoplay_is_deterministic() {
+        let mut first = new_game(5);
+        let mut second = new_game(5);
+        autoplay(StrategyKind::Balanced.strategy(), &mut first, 20);
+        autoplay(StrategyKind::Balanced.strategy(), &mut second, 20);
+        assert_eq!(first.gold, second.gold);
+        assert_eq!(first.man_quantity, second.man_quantity);
+        assert_eq!(first.year, second.year);
+    }
+
+    #[test]
+    fn test_autoplay_stops_when_game_ends() {
+        let mut game = new_game(9);
+        let reports = autoplay(
+            StrategyKind::Rush.strategy(),
+            &mut game,
+            MAX_AUTOPLAY_ROUNDS,
+        );
+        assert!(game.is_game_ended);
+        assert_eq!(reports.last().map(|r| r.game_ended), Some(true));
+    }
+}
@@ -0,0 +1,158 @@
+//! Soldier upkeep and morale
+//! Every round the soldiers are paid and fed from the treasury and the food stores. How well
+//! that goes, and how the army fares in battle, moves its morale; soldiers with low morale
+//! desert.
+
+use rand::Rng;
+
+use crate::game::balance;
+use crate::game::state::GameState;
+
+/// Morale change when every soldier is paid, and when none is
+const MORALE_PAID: i32 = 3;
+const MORALE_UNPAID: i32 = -15;
+/// Morale change when every soldier is fed, and when none is
+const MORALE_FED: i32 = 2;
+const MORALE_HUNGRY: i32 = -10;
+/// Morale change after a battle
+pub const MORALE_VICTORY: i8 = 10;
+pub const MORALE_DEFEAT: i8 = -10;
+
+/// What the army cost this round
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Upkeep {
+    /// Gold the soldiers were owed, and what they got
+    pub wages: i32,
+    pub paid: i32,
+    /// Food the soldiers needed, and what they got
+    pub rations: i32,
+    pub fed: i32,
+}
+
+impl Upkeep {
+    /// What `soldiers` are owed in a round
+    pub fn owed(soldiers: i16) -> Self {
+        let army = &balance::current().army;
+        Self {
+            wages: soldiers as i32 * army.wage,
+            rations: soldiers as i32 * army.ration,
+            ..Default::default()
+        }
+    }
+
+    /// Morale change for this upkeep: the share paid and the share fed each move it
+    /// between their good and bad value
+    pub fn morale_change(&self) -> i8 {
+        let share = |got: i32, owed: i32, good: i32, bad: i32| {
+            if owed == 0 {
+                good
+            } else {
+                bad + (good - bad) * got / owed
+            }
+        };
+        (share(self.paid, self.wages, MORALE_PAID, MORALE_UNPAID)
+            + share(self.fed, self.rations, MORALE_FED, MORALE_HUNGRY)) as i8
+    }
+}
+
+/// The word for a morale value
+pub fn morale_label(morale: i8) -> &'static str {
+    match morale {
+        0..=19 => "Mutinous",
+        20..=39 => "Restless",
+        40..=59 => "Steady",
+        60..=79 => "Loyal",
+        _ => "Devoted",
+    }
+}
+
+impl GameState {
+    pub fn change_morale(&mut self, amount: i8) {
+        self.morale = self.morale.saturating_add(amount).clamp(0, 100);
+    }
+
+    pub fn morale_label(&self) -> &'static str {
+        morale_label(self.morale)
+    }
+
+    /// Pays and feeds the soldiers as far as the treasury and the food stores allow
+    pub fn pay_army(&mut self) -> Upkeep {
+        let mut upkeep = Upkeep::owed(self.soldier_quantity);
+        upkeep.paid = upkeep.wages.min(self.gold.max(0));
+        upkeep.fed = upkeep.rations.min(self.food_quantity.max(0));
+        self.gold -= upkeep.paid;
+        self.food_quantity -= upkeep.fed;
+        upkeep
+    }
+
+    /// Chance in percent that soldiers desert this round; none at or above
+    /// `army.desertion_morale`
+    pub fn desertion_chance(&self) -> i32 {
+        let threshold = balance::current().army.desertion_morale as i32;
+        if self.soldier_quantity == 0 {
+            return 0;
+        }
+        ((threshold - self.morale as i32) * 3).clamp(0, 100)
+    }
+
+    /// Lets unhappy soldiers go. The lower the morale, the more likely it happens and the
+    /// more leave. Returns how many deserted.
+    pub fn desert<R: Rng + ?Sized>(&mut self, rng: &mut R) -> i16 {
+        let chance = self.desertion_chance();
+        if chance == 0 || rng.gen_range(0..100) >= chance {
+            return 0;
+        }
+        let deserters = (self.soldier_quantity as i32 * chance / 300).max(1) as i16;
+        self.change_soldiers(-deserters);
+        deserters
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::state::test_game;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_upkeep_and_morale() {
+        let army = &balance::current().army;
+        let mut game = test_game(5);
+        game.soldier_quantity = 50;
+        let (gold, food) = (game.gold, game.food_quantity);
+        let upkeep = game.pay_army();
+        assert_eq!(upkeep.paid, 50 * army.wage);
+        assert_eq!(game.gold, gold - upkeep.paid);
+        assert_eq!(game.food_quantity, food - 50 * army.ration);
+        assert_eq!(upkeep.morale_change(), (MORALE_PAID + MORALE_FED) as i8);
+
+        game.gold = 0;
+        game.food_quantity = 0;
+        let upkeep = game.pay_army();
+        assert_eq!((upkeep.paid, upkeep.fed), (0, 0));
+        assert_eq!(
+            upkeep.morale_change(),
+            (MORALE_UNPAID + MORALE_HUNGRY) as i8
+        );
+
+        game.change_morale(-100);
+        assert_eq!(game.morale, 0);
+        assert_eq!(game.morale_label(), "Mutinous");
+    }
+
+    #[test]
+    fn test_low_morale_deserts() {
+        let mut game = test_game(5);
+        game.soldier_quantity = 100;
+        assert_eq!(game.desertion_chance(), 0);
+        assert_eq!(game.desert(&mut StdRng::seed_from_u64(1)), 0);
+
+        game.morale = 0;
+        assert!(game.desertion_chance() > 0);
+        let deserted: i16 = (0..20)
+            .map(|seed| game.clone().desert(&mut StdRng::seed_from_u64(seed)))
+            .sum();
+        assert!(deserted > 0);
+    }
+}
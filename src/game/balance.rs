@@ -0,0 +1,615 @@
+//! Balance configuration
+//! Every tunable number of the game. The built-in default matches the original game; a TOML
+//! or JSON file can replace it at startup so variants can be tuned without rebuilding.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::game::state::Difficulty;
+
+/// Highest tax and food supply level; the effect tables have one row per level from 0
+pub const MAX_LEVEL: u8 = 5;
+
+/// Castle level at which the castle can no longer be upgraded
+pub const MAX_CASTLE_LEVEL: u8 = 8;
+
+static CURRENT: OnceLock<Balance> = OnceLock::new();
+
+/// The balance every game uses: the installed one, or the built-in default
+pub fn current() -> &'static Balance {
+    CURRENT.get_or_init(Balance::default)
+}
+
+/// Replaces the built-in default. Must happen before the first game starts;
+/// returns `false` if a balance is already in use.
+pub fn install(balance: Balance) -> bool {
+    CURRENT.set(balance).is_ok()
+}
+
+#[derive(Debug)]
+pub enum BalanceError {
+    Io(io::Error),
+    Parse(String),
+    Invalid { field: String, reason: String },
+}
+
+impl fmt::Display for BalanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BalanceError::Io(err) => write!(f, "Could not read balance file: {}", err),
+            BalanceError::Parse(err) => write!(f, "Balance file is malformed: {}", err),
+            BalanceError::Invalid { field, reason } => {
+                write!(f, "Invalid balance value {}: {}", field, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BalanceError {}
+
+impl From<io::Error> for BalanceError {
+    fn from(err: io::Error) -> Self {
+        BalanceError::Io(err)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Balance {
+    pub start: StartingResources,
+    pub prices: Prices,
+    pub difficulty: DifficultyModifiers,
+    /// Requirements for each promotion, from Baron to Emperor
+    pub grades: [GradeRequirement; 5],
+    /// Popularity and population effect of each tax level
+    pub tax_effects: [TaxEffect; 6],
+    /// Popularity and population effect of each food supply level
+    pub food_effects: [FoodEffect; 6],
+    pub army: ArmyParameters,
+    pub upkeep: BuildingUpkeep,
+    pub events: EventParameters,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StartingResources {
+    pub year: u16,
+    /// The game is lost once this year has passed
+    pub end_year: u16,
+    pub gold: i32,
+    pub food: i32,
+    pub citizens: i32,
+    pub farms: i16,
+    pub popularity: i8,
+    pub taxes_level: u8,
+    pub food_supply: u8,
+    /// Most iron, weapons or armor that can be stored
+    pub trade_limit: i16,
+}
+
+/// Building costs and the base market prices that trade prices fluctuate around
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Prices {
+    pub castle: i32,
+    pub farm: i32,
+    pub mine: i32,
+    pub smithy: i32,
+    pub market: i32,
+    /// Share of a building's price refunded when it is demolished, in percent
+    pub demolish_refund: u8,
+    pub soldier: i32,
+    /// Per 100 food
+    pub food: i16,
+    pub iron: i16,
+    pub armor: i16,
+    pub weapon: i16,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DifficultyModifiers {
+    pub easy: DifficultyModifier,
+    pub medium: DifficultyModifier,
+    pub hard: DifficultyModifier,
+}
+
+impl DifficultyModifiers {
+    /// The modifier of a standard difficulty; custom games set their own starting values
+    pub fn for_difficulty(&self, difficulty: Difficulty) -> Option<&DifficultyModifier> {
+        match difficulty {
+            Difficulty::Easy => Some(&self.easy),
+            Difficulty::Medium => Some(&self.medium),
+            Difficulty::Hard => Some(&self.hard),
+            Difficulty::Custom => None,
+        }
+    }
+}
+
+/// Added to the starting resources; negative values make the start harder
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DifficultyModifier {
+    pub gold: i32,
+    pub food: i32,
+    pub citizens: i32,
+    pub farms: i16,
+    pub popularity: i8,
+    /// Later start, so fewer years until the game ends
+    pub years: u16,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GradeRequirement {
+    pub citizens: i32,
+    pub popularity: i32,
+    pub castle_level: i32,
+    pub soldiers: i32,
+    pub gold: i32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaxEffect {
+    pub popularity: i8,
+    /// Multiplied by a roll of 4-7 to get the population change
+    pub population_multiplier: i32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FoodEffect {
+    pub popularity: i8,
+    /// Population change in percent of the current population
+    pub population_percent: i32,
+}
+
+/// Event sizes are `roll(0..value * percent / 100) + base`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EventParameter {
+    pub percent: i32,
+    pub base: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArmyParameters {
+    /// Gold each soldier is paid every round
+    pub wage: i32,
+    /// Food each soldier eats every round
+    pub ration: i32,
+    /// Morale of a new army, 0 to 100
+    pub morale: i8,
+    /// Below this morale soldiers start to desert
+    pub desertion_morale: i8,
+}
+
+/// Gold each building costs every round
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BuildingUpkeep {
+    pub farm: i32,
+    pub mine: i32,
+    pub smithy: i32,
+    pub market: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EventParameters {
+    /// Chance that a random event happens at the end of a round
+    pub chance: f32,
+    pub food: EventParameter,
+    pub gold: EventParameter,
+    pub citizens: EventParameter,
+    pub soldiers: EventParameter,
+    /// Farms lost to a fire, rolled between the two (inclusive)
+    pub farms_min: i16,
+    pub farms_max: i16,
+}
+
+impl Default for Balance {
+    fn default() -> Self {
+        Self {
+            start: StartingResources::default(),
+            prices: Prices::default(),
+            difficulty: DifficultyModifiers::default(),
+            grades: [
+                GradeRequirement {
+                    citizens: 1400,
+                    popularity: 65,
+                    castle_level: 0,
+                    soldiers: 0,
+                    gold: 0,
+                },
+                GradeRequirement {
+                    citizens: 2000,
+                    popularity: 70,
+                    castle_level: 1,
+                    soldiers: 10,
+                    gold: 0,
+                },
+                GradeRequirement {
+                    citizens: 3000,
+                    popularity: 75,
+                    castle_level: 2,
+                    soldiers: 25,
+                    gold: 0,
+                },
+                GradeRequirement {
+                    citizens: 5000,
+                    popularity: 80,
+                    castle_level: 6,
+                    soldiers: 200,
+                    gold: 100000,
+                },
+                GradeRequirement {
+                    citizens: 10000,
+                    popularity: 90,
+                    castle_level: 8,
+                    soldiers: 500,
+                    gold: 1000000,
+                },
+            ],
+            tax_effects: [(5, 10), (3, 8), (2, 4), (0, 1), (-3, -5), (-5, -9)].map(
+                |(popularity, population_multiplier)| TaxEffect {
+                    popularity,
+                    population_multiplier,
+                },
+            ),
+            food_effects: [(-5, -8), (-2, -6), (-1, -3), (0, 1), (1, 4), (3, 7)].map(
+                |(popularity, population_percent)| FoodEffect {
+                    popularity,
+                    population_percent,
+                },
+            ),
+            army: ArmyParameters::default(),
+            upkeep: BuildingUpkeep::default(),
+            events: EventParameters::default(),
+        }
+    }
+}
+
+impl Default for StartingResources {
+    fn default() -> Self {
+        Self {
+            year: 1440,
+            end_year: 1500,
+            gold: 2000,
+            food: 2500,
+            citizens: 1000,
+            farms: 1,
+            popularity: 72,
+            taxes_level: 3,
+            food_supply: 3,
+            trade_limit: 20000,
+        }
+    }
+}
+
+impl Default for Prices {
+    fn default() -> Self {
+        Self {
+            castle: 5000,
+            farm: 1000,
+            mine: 3000,
+            smithy: 3000,
+            market: 2000,
+            demolish_refund: 50,
+            soldier: 100,
+            food: 40,
+            iron: 60,
+            armor: 250,
+            weapon: 150,
+        }
+    }
+}
+
+impl Default for DifficultyModifiers {
+    fn default() -> Self {
+        Self {
+            easy: DifficultyModifier::default(),
+            medium: DifficultyModifier {
+                gold: -500,
+                food: -800,
+                citizens: -250,
+                farms: 0,
+                popularity: -5,
+                years: 5,
+            },
+            hard: DifficultyModifier {
+                gold: -1000,
+                food: -1600,
+                citizens: -500,
+                farms: -1,
+                popularity: -10,
+                years: 10,
+            },
+        }
+    }
+}
+
+impl Default for ArmyParameters {
+    fn default() -> Self {
+        Self {
+            wage: 2,
+            ration: 1,
+            morale: 60,
+            desertion_morale: 40,
+        }
+    }
+}
+
+impl Default for BuildingUpkeep {
+    fn default() -> Self {
+        Self {
+            farm: 10,
+            mine: 30,
+            smithy: 30,
+            market: 20,
+        }
+    }
+}
+
+impl Default for EventParameters {
+    fn default() -> Self {
+        Self {
+            chance: 0.5,
+            food: EventParameter {
+                percent: 30,
+                base: 200,
+            },
+            gold: EventParameter {
+                percent: 40,
+                base: 50,
+            },
+            citizens: EventParameter {
+                percent: 9,
+                base: 5,
+            },
+            soldiers: EventParameter {
+                percent: 10,
+                base: 2,
+            },
+            farms_min: 2,
+            farms_max: 4,
+        }
+    }
+}
+
+impl Balance {
+    /// Reads a balance file; `.json` files are JSON, anything else is TOML.
+    /// Missing fields keep their default value.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BalanceError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        let balance = if path.extension().is_some_and(|ext| ext == "json") {
+            Self::from_json(&text)?
+        } else {
+            Self::from_toml(&text)?
+        };
+        balance.validate()?;
+        Ok(balance)
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, BalanceError> {
+        toml::from_str(text).map_err(|err| BalanceError::Parse(err.to_string()))
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, BalanceError> {
+        serde_json::from_str(text).map_err(|err| BalanceError::Parse(err.to_string()))
+    }
+
+    /// Rejects values the simulation cannot work with
+    pub fn validate(&self) -> Result<(), BalanceError> {
+        check(
+            self.start.year < self.start.end_year,
+            "start.year",
+            "must be before start.end_year",
+        )?;
+        check(
+            self.start.taxes_level <= MAX_LEVEL,
+            "start.taxes_level",
+            "must be between 0 and 5",
+        )?;
+        check(
+            self.start.food_supply <= MAX_LEVEL,
+            "start.food_supply",
+            "must be between 0 and 5",
+        )?;
+        check(
+            self.start.trade_limit > 0,
+            "start.trade_limit",
+            "must be positive",
+        )?;
+
+        let prices = &self.prices;
+        for (field, price) in [
+            ("prices.castle", prices.castle),
+            ("prices.farm", prices.farm),
+            ("prices.mine", prices.mine),
+            ("prices.smithy", prices.smithy),
+            ("prices.market", prices.market),
+            ("prices.soldier", prices.soldier),
+            ("prices.iron", prices.iron as i32),
+            ("prices.armor", prices.armor as i32),
+            ("prices.weapon", prices.weapon as i32),
+        ] {
+            check(price > 0, field, "must be positive")?;
+        }
+        check(
+            prices.demolish_refund <= 100,
+            "prices.demolish_refund",
+            "must be between 0 and 100",
+        )?;
+        // The food price is divided by the harvest and still needs room to fluctuate
+        check(prices.food >= 10, "prices.food", "must be at least 10")?;
+
+        for (name, modifier) in [
+            ("easy", &self.difficulty.easy),
+            ("medium", &self.difficulty.medium),
+            ("hard", &self.difficulty.hard),
+        ] {
+            self.validate_start(name, modifier)?;
+        }
+
+        for (index, grade) in self.grades.iter().enumerate() {
+            let field = |name: &str| format!("grades[{}].{}", index, name);
+            check(
+                (0..=100).contains(&grade.popularity),
+                &field("popularity"),
+                "must be between 0 and 100",
+            )?;
+            check(
+                (0..=MAX_CASTLE_LEVEL as i32).contains(&grade.castle_level),
+                &field("castle_level"),
+                "must be between 0 and 8",
+            )?;
+            for (name, value) in [
+                ("citizens", grade.citizens),
+                ("soldiers", grade.soldiers),
+                ("gold", grade.gold),
+            ] {
+                check(value >= 0, &field(name), "must not be negative")?;
+            }
+        }
+
+        let army = &self.army;
+        check(army.wage >= 0, "army.wage", "must not be negative")?;
+        check(army.ration >= 0, "army.ration", "must not be negative")?;
+        check(
+            (0..=100).contains(&army.morale),
+            "army.morale",
+            "must be between 0 and 100",
+        )?;
+        check(
+            (0..=100).contains(&army.desertion_morale),
+            "army.desertion_morale",
+            "must be between 0 and 100",
+        )?;
+
+        let upkeep = &self.upkeep;
+        for (field, gold) in [
+            ("upkeep.farm", upkeep.farm),
+            ("upkeep.mine", upkeep.mine),
+            ("upkeep.smithy", upkeep.smithy),
+            ("upkeep.market", upkeep.market),
+        ] {
+            check(gold >= 0, field, "must not be negative")?;
+        }
+
+        let events = &self.events;
+        check(
+            (0.0..=1.0).contains(&events.chance),
+            "events.chance",
+            "must be between 0 and 1",
+        )?;
+        for (name, parameter) in [
+            ("food", events.food),
+            ("gold", events.gold),
+            ("citizens", events.citizens),
+            ("soldiers", events.soldiers),
+        ] {
+            check(
+                parameter.percent >= 0 && parameter.base >= 0,
+                &format!("events.{}", name),
+                "percent and base must not be negative",
+            )?;
+        }
+        check(
+            0 <= events.farms_min && events.farms_min <= events.farms_max,
+            "events.farms_min",
+            "must not be negative or above events.farms_max",
+        )?;
+
+        Ok(())
+    }
+
+    /// The starting resources after a difficulty modifier must still make a playable game
+    fn validate_start(
+        &self,
+        name: &str,
+        modifier: &DifficultyModifier,
+    ) -> Result<(), BalanceError> {
+        let start = &self.start;
+        let field = |resource: &str| format!("start.{0} + difficulty.{1}.{0}", resource, name);
+        check(
+            start.gold as i64 + modifier.gold as i64 >= 0,
+            &field("gold"),
+            "leaves a negative starting treasury",
+        )?;
+        check(
+            start.food as i64 + modifier.food as i64 >= 0,
+            &field("food"),
+            "leaves negative starting food",
+        )?;
+        check(
+            start.citizens as i64 + modifier.citizens as i64 > 0,
+            &field("citizens"),
+            "leaves no citizens",
+        )?;
+        check(
+            start.farms as i32 + modifier.farms as i32 >= 0,
+            &field("farms"),
+            "leaves a negative number of farms",
+        )?;
+        check(
+            (0..=100).contains(&(start.popularity as i32 + modifier.popularity as i32)),
+            &field("popularity"),
+            "moves the starting popularity outside 0-100",
+        )?;
+        check(
+            (start.year as u32 + modifier.years as u32) < start.end_year as u32,
+            &format!("start.year + difficulty.{}.years", name),
+            "starts the game after start.end_year",
+        )
+    }
+}
+
+fn check(ok: bool, field: &str, reason: &str) -> Result<(), BalanceError> {
+    if ok {
+        Ok(())
+    } else {
+        Err(BalanceError::Invalid {
+            field: field.to_string(),
+            reason: reason.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shipped_file_matches_default() {
+        let shipped = Balance::load(concat!(env!("CARGO_MANIFEST_DIR"), "/balance.toml")).unwrap();
+        assert_eq!(shipped, Balance::default());
+    }
+
+    #[test]
+    fn test_partial_file_and_validation() {
+        let balance = Balance::from_toml("[start]\ngold = 5000\n").unwrap();
+        assert_eq!(balance.start.gold, 5000);
+        assert_eq!(balance.prices, Prices::default());
+
+        assert!(matches!(
+            Balance::from_toml("[start]\ngould = 5000\n"),
+            Err(BalanceError::Parse(_))
+        ));
+
+        let mut balance = Balance::default();
+        balance.difficulty.hard.citizens = -1000;
+        let err = balance.validate().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid balance value start.citizens + difficulty.hard.citizens: leaves no citizens"
+        );
+    }
+}
@@ -0,0 +1,362 @@
+//! Battles against the Dark Emperor
+//! The player's army (soldiers, armor, spare weapons and, when defending, the castle) is
+//! measured against an enemy that grows with the years and the player's rank. Both sides
+//! roll 80-120% of their strength; the stronger roll wins. Battles come from events or
+//! from raids the player launches.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::game::army::{MORALE_DEFEAT, MORALE_VICTORY};
+use crate::game::chronicle::ChronicleKind;
+use crate::game::error::GameError;
+use crate::game::events::EventEffects;
+use crate::game::state::GameState;
+
+/// Strength of one soldier
+const SOLDIER_POWER: i32 = 10;
+/// Extra strength of a soldier wearing armor
+const ARMOR_POWER: i32 = 5;
+/// Strength of a spare weapon in stock, handed out to the levy (at most one per soldier)
+const SPARE_WEAPON_POWER: i32 = 2;
+/// Defense bonus of each castle level, in percent
+const CASTLE_DEFENSE_PERCENT: i32 = 10;
+
+/// Enemy strength in the first year, and what each year and each rank adds
+const ENEMY_BASE: i32 = 60;
+const ENEMY_PER_YEAR: i32 = 10;
+const ENEMY_PER_GRADE: i32 = 200;
+/// A raid meets an outpost with this share of the enemy's strength, in percent
+const OUTPOST_PERCENT: i32 = 60;
+
+/// Share of the soldiers that fall in an even battle, in percent
+const LOSSES_WON_PERCENT: i32 = 15;
+const LOSSES_LOST_PERCENT: i32 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BattleKind {
+    /// The Dark Emperor attacks; the castle helps and a defeat costs tribute
+    Defense,
+    /// The player attacks an outpost for loot
+    Raid,
+}
+
+impl fmt::Display for BattleKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BattleKind::Defense => write!(f, "Defense"),
+            BattleKind::Raid => write!(f, "Raid"),
+        }
+    }
+}
+
+/// What the player's army is made of, and the strength each part adds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArmyStrength {
+    pub soldiers: i16,
+    pub armored: i16,
+    /// Spare weapons that count, at most one per soldier
+    pub spare_weapons: i16,
+    pub castle_level: u8,
+    pub soldier_power: i32,
+    pub armor_power: i32,
+    pub weapon_power: i32,
+    /// Added by the castle when defending
+    pub castle_bonus: i32,
+    pub total: i32,
+}
+
+impl ArmyStrength {
+    pub fn of(game: &GameState, kind: BattleKind) -> Self {
+        let soldiers = game.soldier_quantity;
+        let armored = game.armored_soldier_quantity.min(soldiers);
+        let spare_weapons = game.weapon_quantity.min(soldiers);
+        let soldier_power = soldiers as i32 * SOLDIER_POWER;
+        let armor_power = armored as i32 * ARMOR_POWER;
+        let weapon_power = spare_weapons as i32 * SPARE_WEAPON_POWER;
+        let base = soldier_power + armor_power + weapon_power;
+        let castle_bonus = match kind {
+            BattleKind::Defense => base * game.castle_level as i32 * CASTLE_DEFENSE_PERCENT / 100,
+            BattleKind::Raid => 0,
+        };
+        Self {
+            soldiers,
+            armored,
+            spare_weapons,
+            castle_level: game.castle_level,
+            soldier_power,
+            armor_power,
+            weapon_power,
+            castle_bonus,
+            total: base + castle_bonus,
+        }
+    }
+}
+
+/// Strength of the Dark Emperor's force, before the roll
+pub fn enemy_strength(game: &GameState, kind: BattleKind) -> i32 {
+    let strength =
+        ENEMY_BASE + game.round as i32 * ENEMY_PER_YEAR + game.grade as i32 * ENEMY_PER_GRADE;
+    match kind {
+        BattleKind::Defense => strength,
+        BattleKind::Raid => strength * OUTPOST_PERCENT / 100,
+    }
+}
+
+/// The full account of one battle
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BattleReport {
+    pub kind: BattleKind,
+    pub year: u16,
+    pub army: ArmyStrength,
+    pub enemy: i32,
+    /// Strength after the 80-120% roll
+    pub army_roll: i32,
+    pub enemy_roll: i32,
+    pub won: bool,
+    pub soldiers_lost: i16,
+    /// Soldiers that would have fallen without armor
+    pub armor_saved: i16,
+    pub enemy_lost: i32,
+    /// Everything the battle changed, including the fallen soldiers
+    pub effects: EventEffects,
+    /// The course of the battle, one sentence each
+    pub lines: Vec<String>,
+}
+
+impl BattleReport {
+    pub fn title(&self) -> &'static str {
+        match (self.kind, self.won) {
+            (BattleKind::Defense, true) => "The border holds",
+            (BattleKind::Defense, false) => "The border falls",
+            (BattleKind::Raid, true) => "A successful raid",
+            (BattleKind::Raid, false) => "A failed raid",
+        }
+    }
+
+    pub fn outcome(&self) -> &'static str {
+        if self.won {
+            "Victory"
+        } else {
+            "Defeat"
+        }
+    }
+
+    /// The lines as one text
+    pub fn summary(&self) -> String {
+        self.lines.join(" ")
+    }
+}
+
+/// Fights a battle in `year`, applies its outcome to `game` and notes it in the chronicle
+pub fn fight<R: Rng + ?Sized>(
+    game: &mut GameState,
+    kind: BattleKind,
+    year: u16,
+    rng: &mut R,
+) -> BattleReport {
+    let army = ArmyStrength::of(game, kind);
+    let enemy = enemy_strength(game, kind);
+    let army_roll = army.total * rng.gen_range(80..=120) / 100;
+    let enemy_roll = enemy * rng.gen_range(80..=120) / 100;
+    let won = army_roll > enemy_roll;
+
+    // The winner loses fewer men, and the harder a side was pressed, the more it loses.
+    // `pressure` is 100 in an even battle and goes from 0 to 200.
+    let (percent, enemy_percent) = if won {
+        (LOSSES_WON_PERCENT, LOSSES_LOST_PERCENT)
+    } else {
+        (LOSSES_LOST_PERCENT, LOSSES_WON_PERCENT)
+    };
+    let pressure = enemy_roll * 200 / (army_roll + enemy_roll).max(1);
+    let losses = (army.soldiers as i32 * percent * pressure / 10000) as i16;
+    let soldiers_lost = game.battle_losses(losses);
+    let armor_saved = losses.min(army.soldiers) - soldiers_lost;
+    let enemy_lost = enemy / SOLDIER_POWER * enemy_percent * (200 - pressure) / 10000;
+
+    let mut effects = EventEffects {
+        soldier_change: -soldiers_lost,
+        weapon_change: -soldiers_lost,
+        morale_change: if won { MORALE_VICTORY } else { MORALE_DEFEAT },
+        ..Default::default()
+    };
+    let mut lines = vec![match kind {
+        BattleKind::Defense => format!(
+            "The Dark Emperor's army of about {} men attacks your border.",
+            enemy / SOLDIER_POWER
+        ),
+        BattleKind::Raid => format!(
+            "Your {} soldiers march on an outpost of about {} men.",
+            army.soldiers,
+            enemy / SOLDIER_POWER
+        ),
+    }];
+    if army.castle_bonus > 0 {
+        lines.push(format!(
+            "Your castle adds {} to your strength.",
+            army.castle_bonus
+        ));
+    }
+    lines.push(format!(
+        "Your army fights with a strength of {} against {}.",
+        army_roll, enemy_roll
+    ));
+
+    match (kind, won) {
+        (BattleKind::Defense, true) => {
+            effects.gold_change = enemy * 2;
+            effects.popularity_change = 3;
+            lines.push(format!(
+                "The enemy flees and leaves {} gold behind.",
+                effects.gold_change
+            ));
+        }
+        (BattleKind::Defense, false) => {
+            effects.gold_change = -(enemy * 3).min(game.gold);
+            effects.population_change = -(enemy / SOLDIER_POWER).min(game.man_quantity);
+            effects.popularity_change = -8;
+            lines.push(format!(
+                "The enemy kills {} inhabitants and takes {} gold as tribute.",
+                -effects.population_change, -effects.gold_change
+            ));
+        }
+        (BattleKind::Raid, true) => {
+            effects.gold_change = enemy * 4;
+            effects.food_change = enemy * 5;
+            effects.popularity_change = 2;
+            lines.push(format!(
+                "The outpost falls. Your soldiers bring back {} gold and {} food.",
+                effects.gold_change, effects.food_change
+            ));
+        }
+        (BattleKind::Raid, false) => {
+            effects.popularity_change = -4;
+            lines.push("The outpost holds and your soldiers retreat.".to_string());
+        }
+    }
+    if soldiers_lost > 0 {
+        lines.push(format!("{} of your soldiers fall.", soldiers_lost));
+    }
+    if armor_saved > 0 {
+        lines.push(format!("Armor saves {} soldiers.", armor_saved));
+    }
+
+    effects.apply_to_state(game);
+    let report = BattleReport {
+        kind,
+        year,
+        army,
+        enemy,
+        army_roll,
+        enemy_roll,
+        won,
+        soldiers_lost,
+        armor_saved,
+        enemy_lost,
+        effects,
+        lines,
+    };
+    game.chronicle_entry(
+        report.year,
+        ChronicleKind::Battle,
+        report.title(),
+        report.summary(),
+        report.effects.describe(),
+    );
+    game.last_battle = Some(report.clone());
+    report
+}
+
+/// Sends the army on a raid, once per round, using the game's seeded generator
+pub fn raid(game: &mut GameState) -> Result<BattleReport, GameError> {
+    if game.soldier_quantity == 0 {
+        return Err(GameError::NotEnoughSoldiers);
+    }
+    if game.has_raided_this_round {
+        return Err(GameError::AlreadyRaided);
+    }
+    let mut rng = game.rng.clone();
+    let report = fight(game, BattleKind::Raid, game.year, &mut rng);
+    game.rng = rng;
+    game.has_raided_this_round = true;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::state::test_game;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_army_and_enemy_strength() {
+        let mut game = test_game(5);
+        game.soldier_quantity = 20;
+        game.armored_soldier_quantity = 10;
+        game.weapon_quantity = 5;
+        game.castle_level = 2;
+
+        let defense = ArmyStrength::of(&game, BattleKind::Defense);
+        assert_eq!(defense.soldier_power + defense.armor_power, 250);
+        assert_eq!(defense.weapon_power, 10);
+        assert_eq!(defense.castle_bonus, 52);
+        assert_eq!(defense.total, 312);
+        assert_eq!(ArmyStrength::of(&game, BattleKind::Raid).total, 260);
+
+        assert_eq!(enemy_strength(&game, BattleKind::Defense), ENEMY_BASE);
+        game.round = 10;
+        game.grade = 1;
+        assert_eq!(enemy_strength(&game, BattleKind::Defense), 360);
+        assert_eq!(enemy_strength(&game, BattleKind::Raid), 216);
+    }
+
+    #[test]
+    fn test_battles_win_and_lose() {
+        let mut strong = test_game(5);
+        strong.soldier_quantity = 100;
+        strong.weapon_quantity = 100;
+        let (gold, year) = (strong.gold, strong.year);
+        let report = fight(
+            &mut strong,
+            BattleKind::Defense,
+            year,
+            &mut StdRng::seed_from_u64(1),
+        );
+        assert!(report.won);
+        assert!(strong.gold > gold);
+        assert_eq!(report.effects.morale_change, MORALE_VICTORY);
+        assert_eq!(strong.soldier_quantity, 100 - report.soldiers_lost);
+        assert_eq!(strong.last_battle, Some(report));
+        assert_eq!(strong.chronicle.last().unwrap().kind, ChronicleKind::Battle);
+
+        let mut weak = test_game(5);
+        weak.soldier_quantity = 1;
+        let (citizens, year) = (weak.man_quantity, weak.year);
+        let report = fight(
+            &mut weak,
+            BattleKind::Defense,
+            year,
+            &mut StdRng::seed_from_u64(1),
+        );
+        assert!(!report.won);
+        assert!(weak.man_quantity < citizens);
+        assert!(report.effects.gold_change < 0);
+    }
+
+    #[test]
+    fn test_raid_once_per_round() {
+        let mut game = test_game(5);
+        assert_eq!(raid(&mut game), Err(GameError::NotEnoughSoldiers));
+
+        game.soldier_quantity = 50;
+        game.weapon_quantity = 50;
+        let report = raid(&mut game).unwrap();
+        assert_eq!(report.kind, BattleKind::Raid);
+        assert_eq!(raid(&mut game), Err(GameError::AlreadyRaided));
+
+        game.finish_round();
+        assert!(raid(&mut game).is_ok());
+    }
+}
@@ -0,0 +1,333 @@
+//! Building upkeep and condition
+//! Every round each farm, mine, smithy and market costs upkeep in gold. When the treasury
+//! cannot cover it, the buildings fall into disrepair and produce less, until they are
+//! repaired. Condition is kept per kind of building, as the average of all of that kind.
+//! Demolished buildings refund part of their price.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::game::balance;
+use crate::game::error::GameError;
+use crate::game::state::GameState;
+
+pub const FULL_CONDITION: u8 = 100;
+/// Buildings never fall below this condition, in percent
+pub const MIN_CONDITION: u8 = 25;
+/// Condition lost in a round in which none of the upkeep is paid
+const DECAY: i32 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Building {
+    Farm,
+    Mine,
+    Smithy,
+    Market,
+}
+
+impl Building {
+    pub const ALL: [Building; 4] = [
+        Building::Farm,
+        Building::Mine,
+        Building::Smithy,
+        Building::Market,
+    ];
+
+    pub fn plural(self) -> &'static str {
+        match self {
+            Building::Farm => "farms",
+            Building::Mine => "mines",
+            Building::Smithy => "smithies",
+            Building::Market => "markets",
+        }
+    }
+
+    /// Gold each building of this kind costs every round
+    pub fn upkeep(self) -> i32 {
+        let upkeep = &balance::current().upkeep;
+        match self {
+            Building::Farm => upkeep.farm,
+            Building::Mine => upkeep.mine,
+            Building::Smithy => upkeep.smithy,
+            Building::Market => upkeep.market,
+        }
+    }
+
+    /// The price of this kind of building in the balance file
+    fn base_price(self) -> i32 {
+        let prices = &balance::current().prices;
+        match self {
+            Building::Farm => prices.farm,
+            Building::Mine => prices.mine,
+            Building::Smithy => prices.smithy,
+            Building::Market => prices.market,
+        }
+    }
+}
+
+impl fmt::Display for Building {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Building::Farm => write!(f, "farm"),
+            Building::Mine => write!(f, "mine"),
+            Building::Smithy => write!(f, "smithy"),
+            Building::Market => write!(f, "market"),
+        }
+    }
+}
+
+/// Condition of each kind of building in percent; at 100 they produce in full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BuildingCondition {
+    pub farms: u8,
+    pub mines: u8,
+    pub smithies: u8,
+    pub markets: u8,
+}
+
+impl Default for BuildingCondition {
+    fn default() -> Self {
+        Self {
+            farms: FULL_CONDITION,
+            mines: FULL_CONDITION,
+            smithies: FULL_CONDITION,
+            markets: FULL_CONDITION,
+        }
+    }
+}
+
+impl BuildingCondition {
+    pub fn get(&self, building: Building) -> u8 {
+        match building {
+            Building::Farm => self.farms,
+            Building::Mine => self.mines,
+            Building::Smithy => self.smithies,
+            Building::Market => self.markets,
+        }
+    }
+
+    fn get_mut(&mut self, building: Building) -> &mut u8 {
+        match building {
+            Building::Farm => &mut self.farms,
+            Building::Mine => &mut self.mines,
+            Building::Smithy => &mut self.smithies,
+            Building::Market => &mut self.markets,
+        }
+    }
+}
+
+/// What the buildings cost in a round
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Maintenance {
+    /// Gold owed, and what the treasury could pay
+    pub upkeep: i32,
+    pub paid: i32,
+    /// Condition every kind of building lost for the unpaid part
+    pub decay: u8,
+}
+
+impl Maintenance {
+    /// Pays what `gold` allows of `upkeep`; the unpaid share decides the decay
+    pub fn of(upkeep: i32, gold: i32) -> Self {
+        let paid = upkeep.min(gold.max(0));
+        let decay = if upkeep > 0 {
+            ((DECAY * (upkeep - paid) + upkeep - 1) / upkeep) as u8
+        } else {
+            0
+        };
+        Self {
+            upkeep,
+            paid,
+            decay,
+        }
+    }
+}
+
+impl GameState {
+    pub fn building_quantity(&self, building: Building) -> i16 {
+        match building {
+            Building::Farm => self.farm_quantity,
+            Building::Mine => self.mine_quantity,
+            Building::Smithy => self.smithy_quantity,
+            Building::Market => self.market_quantity,
+        }
+    }
+
+    /// What the next building of a kind costs
+    pub fn building_price(&self, building: Building) -> i32 {
+        match building {
+            Building::Farm => self.price_for_farm,
+            Building::Mine => self.price_for_mine,
+            Building::Smithy => self.price_for_smithy,
+            Building::Market => self.price_for_market,
+        }
+    }
+
+    /// Gold refunded for demolishing one building of a kind: `prices.demolish_refund`
+    /// percent of its price
+    pub fn demolish_refund(&self, building: Building) -> i32 {
+        self.building_price(building) * balance::current().prices.demolish_refund as i32 / 100
+    }
+
+    pub fn condition(&self, building: Building) -> u8 {
+        self.building_condition.get(building)
+    }
+
+    /// What `amount` of output comes to at the condition of `building`
+    pub fn with_condition(&self, building: Building, amount: i32) -> i32 {
+        amount * self.condition(building) as i32 / FULL_CONDITION as i32
+    }
+
+    /// Gold all buildings cost every round
+    pub fn building_upkeep(&self) -> i32 {
+        Building::ALL
+            .iter()
+            .map(|&building| self.building_quantity(building) as i32 * building.upkeep())
+            .sum()
+    }
+
+    /// Adds one new building of a kind, in full condition
+    pub(crate) fn add_building(&mut self, building: Building) {
+        let before = self.building_quantity(building).max(0) as i32;
+        let condition = self.building_condition.get_mut(building);
+        *condition = ((*condition as i32 * before + FULL_CONDITION as i32) / (before + 1)) as u8;
+        match building {
+            Building::Farm => self.farm_quantity += 1,
+            Building::Mine => self.mine_quantity += 1,
+            Building::Smithy => self.smithy_quantity += 1,
+            Building::Market => self.market_quantity += 1,
+        }
+    }
+
+    /// Removes one building of a kind; the others keep their condition
+    pub(crate) fn remove_building(&mut self, building: Building) {
+        match building {
+            Building::Farm => self.farm_quantity -= 1,
+            Building::Mine => self.mine_quantity -= 1,
+            Building::Smithy => self.smithy_quantity -= 1,
+            Building::Market => self.market_quantity -= 1,
+        }
+    }
+
+    /// Pays the buildings' upkeep as far as the treasury allows. Every kind of building
+    /// loses condition for the part left unpaid.
+    pub fn maintain_buildings(&mut self) -> Maintenance {
+        let maintenance = Maintenance::of(self.building_upkeep(), self.gold);
+        self.gold -= maintenance.paid;
+        for building in Building::ALL {
+            if self.building_quantity(building) > 0 {
+                let condition = self.building_condition.get_mut(building);
+                *condition = condition
+                    .saturating_sub(maintenance.decay)
+                    .max(MIN_CONDITION);
+            }
+        }
+        maintenance
+    }
+
+    /// Gold it takes to bring every building of a kind back to full condition: half its
+    /// price in the balance file for each, in proportion to the condition lost
+    pub fn repair_cost(&self, building: Building) -> i32 {
+        let missing = (FULL_CONDITION - self.condition(building)) as i32;
+        self.building_quantity(building).max(0) as i32 * building.base_price() * missing
+            / (2 * FULL_CONDITION as i32)
+    }
+
+    pub fn repair(&mut self, building: Building) -> Result<(), GameError> {
+        if self.building_quantity(building) <= 0 || self.condition(building) >= FULL_CONDITION {
+            return Err(GameError::NothingToRepair);
+        }
+        let cost = self.repair_cost(building);
+        if self.gold < cost {
+            return Err(GameError::InsufficientGold { cost });
+        }
+        self.gold -= cost;
+        *self.building_condition.get_mut(building) = FULL_CONDITION;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::state::test_game;
+
+    #[test]
+    fn test_unpaid_upkeep_wears_buildings_down() {
+        let mut game = test_game(5);
+        game.market_quantity = 2;
+        let upkeep = game.building_upkeep();
+        assert_eq!(
+            upkeep,
+            game.farm_quantity as i32 * Building::Farm.upkeep() + 2 * Building::Market.upkeep()
+        );
+
+        let gold = game.gold;
+        let maintenance = game.maintain_buildings();
+        assert_eq!(maintenance.paid, upkeep);
+        assert_eq!(maintenance.decay, 0);
+        assert_eq!(game.gold, gold - upkeep);
+
+        game.gold = upkeep / 2;
+        let maintenance = game.maintain_buildings();
+        assert_eq!(game.gold, 0);
+        assert_eq!(maintenance.decay, (DECAY / 2) as u8);
+        assert_eq!(game.condition(Building::Market), 90);
+        // Kinds with no buildings keep their condition
+        assert_eq!(game.condition(Building::Mine), FULL_CONDITION);
+
+        for _ in 0..10 {
+            game.maintain_buildings();
+        }
+        assert_eq!(game.condition(Building::Farm), MIN_CONDITION);
+        assert_eq!(game.with_condition(Building::Farm, 400), 100);
+    }
+
+    #[test]
+    fn test_repair_and_new_buildings() {
+        let mut game = test_game(5);
+        game.market_quantity = 2;
+        game.building_condition.markets = 60;
+        assert_eq!(game.repair(Building::Mine), Err(GameError::NothingToRepair));
+
+        let cost = game.repair_cost(Building::Market);
+        assert_eq!(cost, 2 * Building::Market.base_price() * 40 / 200);
+        game.gold = cost - 1;
+        assert_eq!(
+            game.repair(Building::Market),
+            Err(GameError::InsufficientGold { cost })
+        );
+        game.gold = cost;
+        game.repair(Building::Market).unwrap();
+        assert_eq!(game.condition(Building::Market), FULL_CONDITION);
+        assert_eq!(game.gold, 0);
+
+        // A new building lifts the average condition
+        game.building_condition.markets = 40;
+        game.add_building(Building::Market);
+        assert_eq!(game.market_quantity, 3);
+        assert_eq!(game.condition(Building::Market), 60);
+    }
+
+    #[test]
+    fn test_demolish_refunds_part_of_the_price() {
+        let mut game = test_game(5);
+        assert_eq!(game.demolish_mine(), Err(GameError::NothingToDemolish));
+        assert!(!game.can_demolish_mine());
+
+        game.market_quantity = 2;
+        game.building_condition.markets = 70;
+        let (gold, price) = (game.gold, game.price_for_market);
+        game.demolish_market().unwrap();
+        assert_eq!(game.market_quantity, 1);
+        assert_eq!(game.gold, gold + price / 2);
+        assert_eq!(game.demolish_refund(Building::Market), price / 2);
+        assert_eq!(game.condition(Building::Market), 70);
+
+        let farms = game.farm_quantity;
+        game.demolish_farm().unwrap();
+        assert_eq!(game.farm_quantity, farms - 1);
+    }
+}
@@ -0,0 +1,210 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::events::EventEffects;
+use crate::game::state::GameState;
+
+/// What a chronicle entry is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChronicleKind {
+    Reign,
+    Event,
+    Decision,
+    Promotion,
+    Castle,
+    Milestone,
+    Battle,
+}
+
+impl ChronicleKind {
+    pub fn css_class(&self) -> &'static str {
+        match self {
+            ChronicleKind::Reign => "chronicle-reign",
+            ChronicleKind::Event => "chronicle-event",
+            ChronicleKind::Decision => "chronicle-decision",
+            ChronicleKind::Promotion => "chronicle-promotion",
+            ChronicleKind::Castle => "chronicle-castle",
+            ChronicleKind::Milestone => "chronicle-milestone",
+            ChronicleKind::Battle => "chronicle-battle",
+        }
+    }
+}
+
+/// One line in the history of a reign
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChronicleEntry {
+    pub year: u16,
+    pub kind: ChronicleKind,
+    pub title: String,
+    pub text: String,
+    /// What it changed, e.g. "-150 food"
+    #[serde(default)]
+    pub effects: Vec<String>,
+}
+
+/// Citizens and gold counts worth a milestone entry the first time they are reached
+const CITIZEN_MILESTONES: [i32; 3] = [2000, 5000, 10000];
+const GOLD_MILESTONES: [i32; 2] = [10000, 100000];
+
+impl EventEffects {
+    /// The non-zero effects, e.g. `["-150 food", "+3% popularity"]`
+    pub fn describe(&self) -> Vec<String> {
+        [
+            (self.gold_change as i64, "gold"),
+            (self.food_change as i64, "food"),
+            (self.population_change as i64, "citizens"),
+            (self.popularity_change as i64, "% popularity"),
+            (self.soldier_change as i64, "soldiers"),
+            (self.weapon_change as i64, "weapons"),
+            (self.farm_change as i64, "farms"),
+            (self.morale_change as i64, "morale"),
+        ]
+        .into_iter()
+        .filter(|(change, _)| *change != 0)
+        .map(|(change, unit)| {
+            let separator = if unit.starts_with('%') { "" } else { " " };
+            format!("{:+}{}{}", change, separator, unit)
+        })
+        .collect()
+    }
+}
+
+impl GameState {
+    /// Adds an entry to the chronicle
+    pub fn chronicle_entry(
+        &mut self,
+        year: u16,
+        kind: ChronicleKind,
+        title: impl Into<String>,
+        text: impl Into<String>,
+        effects: Vec<String>,
+    ) {
+        self.chronicle.push(ChronicleEntry {
+            year,
+            kind,
+            title: title.into(),
+            text: text.into(),
+            effects,
+        });
+    }
+
+    /// Starts the chronicle of a new game
+    pub(crate) fn begin_chronicle(&mut self) {
+        self.chronicle.clear();
+        let text = format!(
+            "{} {} takes the throne with {} citizens and {} gold.",
+            self.get_grade_title(),
+            self.user_name,
+            self.man_quantity,
+            self.gold
+        );
+        self.chronicle_entry(
+            self.year,
+            ChronicleKind::Reign,
+            "The reign begins",
+            text,
+            Vec::new(),
+        );
+    }
+
+    /// Adds entries for milestones reached for the first time
+    pub fn record_milestones(&mut self, year: u16) {
+        let mut reached = Vec::new();
+        for citizens in CITIZEN_MILESTONES {
+            if self.man_quantity >= citizens {
+                reached.push(format!("{} citizens", citizens));
+            }
+        }
+        for gold in GOLD_MILESTONES {
+            if self.gold >= gold {
+                reached.push(format!("{} gold in the treasury", gold));
+            }
+        }
+        for (count, building) in [
+            (self.mine_quantity, "First mine"),
+            (self.smithy_quantity, "First smithy"),
+            (self.market_quantity, "First market"),
+            (self.soldier_quantity, "First soldiers"),
+        ] {
+            if count > 0 {
+                reached.push(building.to_string());
+            }
+        }
+
+        for title in reached {
+            let known = self
+                .chronicle
+                .iter()
+                .any(|entry| entry.kind == ChronicleKind::Milestone && entry.title == title);
+            if !known {
+                let text = format!("{} reached in the year {}.", title, year);
+                self.chronicle_entry(year, ChronicleKind::Milestone, title, text, Vec::new());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::round;
+    use crate::game::state::test_game;
+
+    #[test]
+    fn test_effects_description() {
+        let effects = EventEffects {
+            food_change: -150,
+            popularity_change: 3,
+            ..Default::default()
+        };
+        assert_eq!(effects.describe(), vec!["-150 food", "+3% popularity"]);
+    }
+
+    #[test]
+    fn test_chronicle_records_reign() {
+        let mut game = test_game(7);
+        assert_eq!(game.chronicle.len(), 1);
+        assert_eq!(game.chronicle[0].kind, ChronicleKind::Reign);
+
+        game.gold = 20000;
+        game.upgrade_castle().unwrap();
+        game.build_market().unwrap();
+        round::play_seeded_round(&mut game);
+
+        let kinds: Vec<_> = game.chronicle.iter().map(|entry| entry.kind).collect();
+        assert!(kinds.contains(&ChronicleKind::Castle));
+        let milestones = game
+            .chronicle
+            .iter()
+            .filter(|entry| entry.title == "First market")
+            .count();
+        assert_eq!(milestones, 1);
+
+        round::play_seeded_round(&mut game);
+        let milestones = game
+            .chronicle
+            .iter()
+            .filter(|entry| entry.title == "First market")
+            .count();
+        assert_eq!(milestones, 1);
+
+        let mut game = test_game(7);
+        let first_title = game.get_grade_title();
+        game.man_quantity = 1600;
+        game.popularity_percent = 90;
+        round::play_seeded_round(&mut game);
+        let promotion = game
+            .chronicle
+            .iter()
+            .find(|entry| entry.kind == ChronicleKind::Promotion)
+            .unwrap();
+        assert_ne!(first_title, game.get_grade_title());
+        assert_eq!(
+            promotion.text,
+            format!(
+                "{} Ada is promoted to {}.",
+                first_title,
+                game.get_grade_title()
+            )
+        );
+    }
+}
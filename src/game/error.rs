@@ -0,0 +1,143 @@
+//! Errors returned by player actions on `GameState`
+
+use serde::Serialize;
+use std::fmt;
+
+/// Serialized as `{"error": "insufficient_gold", "cost": 500}` for API clients
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub enum GameError {
+    /// The action costs more gold than the treasury holds
+    InsufficientGold {
+        cost: i32,
+    },
+    NoMarkets,
+    /// Trading this good needs at least `required` markets
+    MarketTierTooLow {
+        required: i16,
+    },
+    /// The stock would exceed the kingdom's trade limit
+    TradeLimit,
+    /// Recruiting would leave fewer than the minimum number of citizens
+    NotEnoughCitizens {
+        minimum: i32,
+    },
+    NotEnoughFood,
+    NotEnoughIron,
+    NotEnoughWeapons,
+    NotEnoughArmor,
+    NotEnoughSoldiers,
+    InvalidQuantity,
+    CastleAtMaxLevel,
+    CastleAlreadyUpgraded,
+    /// The buildings are all in full condition, or there are none
+    NothingToRepair,
+    /// There is no building of this kind left to demolish
+    NothingToDemolish,
+    /// The army has already raided this round
+    AlreadyRaided,
+    /// An event is waiting for the player's decision before the next round
+    DecisionPending,
+    NoDecisionPending,
+    InvalidChoice,
+    /// A custom-difficulty rule is out of range
+    InvalidRule {
+        rule: &'static str,
+        reason: &'static str,
+    },
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameError::InsufficientGold { cost } => {
+                write!(f, "Not enough gold! This costs {} gold.", cost)
+            }
+            GameError::NoMarkets => write!(f, "No markets available!"),
+            GameError::MarketTierTooLow { required } => {
+                write!(f, "You need at least {} markets to trade this!", required)
+            }
+            GameError::TradeLimit => write!(f, "Trade limit reached!"),
+            GameError::NotEnoughCitizens { minimum } => write!(
+                f,
+                "Not enough citizens! (Must keep at least {} citizens)",
+                minimum
+            ),
+            GameError::NotEnoughFood => write!(f, "Not enough food!"),
+            GameError::NotEnoughIron => write!(f, "Not enough iron!"),
+            GameError::NotEnoughWeapons => write!(f, "Not enough weapons!"),
+            GameError::NotEnoughArmor => write!(f, "Not enough armor!"),
+            GameError::NotEnoughSoldiers => write!(f, "Not enough soldiers!"),
+            GameError::InvalidQuantity => write!(f, "Quantity must be positive!"),
+            GameError::CastleAtMaxLevel => write!(f, "Your castle cannot be upgraded any further!"),
+            GameError::CastleAlreadyUpgraded => {
+                write!(f, "The castle has already been upgraded this round!")
+            }
+            GameError::NothingToRepair => {
+                write!(f, "These buildings do not need any repairs!")
+            }
+            GameError::NothingToDemolish => {
+                write!(f, "You have no such building to demolish!")
+            }
+            GameError::AlreadyRaided => {
+                write!(f, "Your army has already raided this round!")
+            }
+            GameError::DecisionPending => {
+                write!(f, "An event is waiting for your decision!")
+            }
+            GameError::NoDecisionPending => write!(f, "There is nothing to decide!"),
+            GameError::InvalidChoice => write!(f, "That choice is not available!"),
+            GameError::InvalidRule { rule, reason } => write!(f, "{} {}!", rule, reason),
+        }
+    }
+}
+
+impl std::error::Error for GameError {}
+
+/// Trade and army quantities are stored as `i16`, so larger requests hit the trade limit
+pub fn small_quantity(quantity: i32) -> Result<i16, GameError> {
+    i16::try_from(quantity).map_err(|_| {
+        if quantity < 0 {
+            GameError::InvalidQuantity
+        } else {
+            GameError::TradeLimit
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameState;
+
+    #[test]
+    fn test_actions_report_typed_errors() {
+        let mut game = GameState {
+            gold: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            game.build_farm(),
+            Err(GameError::InsufficientGold {
+                cost: game.price_for_farm
+            })
+        );
+
+        game.market_quantity = 0;
+        assert_eq!(game.buy_food(100), Err(GameError::NoMarkets));
+
+        game.market_quantity = 3;
+        assert_eq!(
+            game.buy_iron(10),
+            Err(GameError::MarketTierTooLow { required: 5 })
+        );
+        assert_eq!(game.sell_food(-100), Err(GameError::InvalidQuantity));
+    }
+
+    #[test]
+    fn test_small_quantity_does_not_wrap() {
+        assert_eq!(small_quantity(250), Ok(250));
+        assert_eq!(small_quantity(70000), Err(GameError::TradeLimit));
+        assert_eq!(small_quantity(-70000), Err(GameError::InvalidQuantity));
+    }
+}
@@ -0,0 +1,762 @@
+//! Events defined in data files instead of code.
+//!
+//! Every `.toml`, `.json` or `.ron` file in the events directory describes one event:
+//! ```toml
+//! id = "harvest_festival"
+//! title = "Harvest Festival"
+//! description = "Your people celebrate the harvest and eat {-food_change} food rations."
+//! weight = 5
+//! cooldown = 4
+//!
+//! [conditions]
+//! min_markets = 1
+//!
+//! [effects]
+//! food_change = "-min(food_param / 2, food)"
+//! popularity_change = "3"
+//! ```
+//! Effects are formulas over the rolled event parameters (`food_param`, `gold_param`,
+//! `man_param`, `soldier_param`, `farm_param`) and the country (`gold`, `food`,
+//! `citizens`, `soldiers`, ...). Texts can contain `{formula}` placeholders, which may
+//! also use the effect results (`gold_change`, `food_change`, ...).
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+use crate::game::events::{
+    EventBuilder, EventChoice, EventConditions, EventDefinition, EventEffects, EventRegistry,
+    EventRoll, GameEvent,
+};
+use crate::game::state::GameState;
+
+/// Values formulas can use, in the order `variables` fills them in
+const VARIABLES: [&str; 21] = [
+    "food_param",
+    "gold_param",
+    "man_param",
+    "soldier_param",
+    "farm_param",
+    "gold",
+    "food",
+    "citizens",
+    "soldiers",
+    "weapons",
+    "iron",
+    "armor",
+    "farms",
+    "mines",
+    "smithies",
+    "markets",
+    "castle_level",
+    "popularity",
+    "grade",
+    "year",
+    "round",
+];
+
+/// Effects an event can have; texts can use their results after the variables
+const EFFECTS: [&str; 7] = [
+    "gold_change",
+    "food_change",
+    "population_change",
+    "popularity_change",
+    "soldier_change",
+    "weapon_change",
+    "farm_change",
+];
+
+#[derive(Debug)]
+pub enum EventDataError {
+    Io(io::Error),
+    Parse {
+        file: String,
+        reason: String,
+    },
+    Invalid {
+        file: String,
+        field: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for EventDataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventDataError::Io(err) => write!(f, "Could not read event files: {}", err),
+            EventDataError::Parse { file, reason } => {
+                write!(f, "Event file {} is malformed: {}", file, reason)
+            }
+            EventDataError::Invalid {
+                file,
+                field,
+                reason,
+            } => write!(f, "Invalid event {} in {}: {}", field, file, reason),
+        }
+    }
+}
+
+impl std::error::Error for EventDataError {}
+
+impl From<io::Error> for EventDataError {
+    fn from(err: io::Error) -> Self {
+        EventDataError::Io(err)
+    }
+}
+
+/// One event as written in a data file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EventFile {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    #[serde(default)]
+    pub conditions: EventConditions,
+    #[serde(default)]
+    pub cooldown: u16,
+    #[serde(default)]
+    pub max_occurrences: Option<u16>,
+    #[serde(default)]
+    pub effects: EffectFormulas,
+    #[serde(default)]
+    pub choices: Vec<ChoiceFile>,
+}
+
+fn default_weight() -> u32 {
+    EventDefinition::DEFAULT_WEIGHT
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChoiceFile {
+    pub label: String,
+    pub outcome: String,
+    #[serde(default)]
+    pub effects: EffectFormulas,
+}
+
+/// Formulas for the fields of `EventEffects`; effects left out do not change
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EffectFormulas {
+    pub gold_change: Option<String>,
+    pub food_change: Option<String>,
+    pub population_change: Option<String>,
+    pub popularity_change: Option<String>,
+    pub soldier_change: Option<String>,
+    pub weapon_change: Option<String>,
+    pub farm_change: Option<String>,
+}
+
+impl EffectFormulas {
+    fn formulas(&self) -> [&Option<String>; 7] {
+        [
+            &self.gold_change,
+            &self.food_change,
+            &self.population_change,
+            &self.popularity_change,
+            &self.soldier_change,
+            &self.weapon_change,
+            &self.farm_change,
+        ]
+    }
+
+    fn is_empty(&self) -> bool {
+        self.formulas().iter().all(|formula| formula.is_none())
+    }
+}
+
+impl EventFile {
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|err| err.to_string())
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        serde_json::from_str(text).map_err(|err| err.to_string())
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self, String> {
+        ron::from_str(text).map_err(|err| err.to_string())
+    }
+
+    /// Checks the event and turns it into a definition for the registry. Errors name
+    /// the offending field.
+    pub fn compile(&self) -> Result<EventDefinition, (String, String)> {
+        let invalid = |field: &str, reason: &str| Err((field.to_string(), reason.to_string()));
+
+        if self.id.is_empty()
+            || !self
+                .id
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        {
+            return invalid("id", "must be lowercase letters, digits and underscores");
+        }
+        if self.title.trim().is_empty() {
+            return invalid("title", "must not be empty");
+        }
+        if self.max_occurrences == Some(0) {
+            return invalid("max_occurrences", "must be at least 1");
+        }
+        let conditions = &self.conditions;
+        if conditions
+            .max_soldiers
+            .is_some_and(|max| max < conditions.min_soldiers)
+        {
+            return invalid("conditions.max_soldiers", "is below min_soldiers");
+        }
+        if conditions
+            .max_grade
+            .is_some_and(|max| max < conditions.min_grade)
+        {
+            return invalid("conditions.max_grade", "is below min_grade");
+        }
+        if let (Some(first), Some(last)) = (conditions.first_year, conditions.last_year) {
+            if last < first {
+                return invalid("conditions.last_year", "is before first_year");
+            }
+        }
+        if self.choices.is_empty() && self.effects.is_empty() {
+            return invalid("effects", "an event needs effects or choices");
+        }
+        if !self.choices.is_empty() && !self.effects.is_empty() {
+            return invalid("effects", "are ignored when the event has choices");
+        }
+
+        let text = |field: &str, source: &str| {
+            Text::parse(source).map_err(|reason| (field.to_string(), reason))
+        };
+        let effects = |field: &str, formulas: &EffectFormulas| {
+            Effects::parse(formulas)
+                .map_err(|(effect, reason)| (format!("{}.{}", field, effect), reason))
+        };
+
+        let mut choices = Vec::new();
+        for (index, choice) in self.choices.iter().enumerate() {
+            let field = format!("choices[{}]", index);
+            if choice.label.trim().is_empty() {
+                return invalid(&format!("{}.label", field), "must not be empty");
+            }
+            choices.push(ChoiceTemplate {
+                label: text(&format!("{}.label", field), &choice.label)?,
+                outcome: text(&format!("{}.outcome", field), &choice.outcome)?,
+                effects: effects(&format!("{}.effects", field), &choice.effects)?,
+            });
+        }
+
+        let template = EventTemplate {
+            title: text("title", &self.title)?,
+            description: text("description", &self.description)?,
+            effects: effects("effects", &self.effects)?,
+            choices,
+        };
+
+        Ok(EventDefinition {
+            id: self.id.clone(),
+            weight: self.weight,
+            conditions: self.conditions.clone(),
+            cooldown: self.cooldown,
+            max_occurrences: self.max_occurrences,
+            build: EventBuilder::Data(Arc::new(template)),
+        })
+    }
+}
+
+/// Reads every event file in `dir`, in file name order. Files with other extensions
+/// are ignored.
+pub fn load_dir(dir: impl AsRef<Path>) -> Result<Vec<EventDefinition>, EventDataError> {
+    let mut paths: Vec<_> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    paths.sort();
+
+    let mut ids = BTreeSet::new();
+    let mut definitions = Vec::new();
+    for path in paths {
+        let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+        let parse = match extension {
+            "toml" => EventFile::from_toml,
+            "json" => EventFile::from_json,
+            "ron" => EventFile::from_ron,
+            _ => continue,
+        };
+        let file = path.display().to_string();
+        let event = parse(&fs::read_to_string(&path)?).map_err(|reason| EventDataError::Parse {
+            file: file.clone(),
+            reason,
+        })?;
+        let definition = event
+            .compile()
+            .map_err(|(field, reason)| EventDataError::Invalid {
+                file: file.clone(),
+                field,
+                reason,
+            })?;
+        if !ids.insert(definition.id.clone()) {
+            return Err(EventDataError::Invalid {
+                file,
+                field: "id".to_string(),
+                reason: format!("{} is used by another event file", definition.id),
+            });
+        }
+        definitions.push(definition);
+    }
+    Ok(definitions)
+}
+
+/// The standard events plus the events in `dir`. A file whose id matches a standard
+/// event replaces it. A missing directory just gives the standard events.
+pub fn load_registry(dir: impl AsRef<Path>) -> Result<EventRegistry, EventDataError> {
+    let mut registry = EventRegistry::standard();
+    if !dir.as_ref().is_dir() {
+        return Ok(registry);
+    }
+    for definition in load_dir(dir)? {
+        registry.register(definition);
+    }
+    Ok(registry)
+}
+
+/// A compiled event file, ready to build events
+#[derive(Debug)]
+pub struct EventTemplate {
+    title: Text,
+    description: Text,
+    effects: Effects,
+    choices: Vec<ChoiceTemplate>,
+}
+
+#[derive(Debug)]
+struct ChoiceTemplate {
+    label: Text,
+    outcome: Text,
+    effects: Effects,
+}
+
+impl EventTemplate {
+    /// Builds the event, or `None` when it would change nothing or a formula fails
+    /// (a division by zero, say)
+    pub fn build(&self, state: &GameState, roll: &EventRoll) -> Option<GameEvent> {
+        let values = variables(state, roll);
+
+        let mut choices = Vec::new();
+        for choice in &self.choices {
+            let results = choice.effects.evaluate(&values)?;
+            let text_values = [&values[..], &results[..]].concat();
+            choices.push(EventChoice {
+                label: choice.label.render(&text_values)?,
+                outcome: choice.outcome.render(&text_values)?,
+                effects: to_effects(&results),
+            });
+        }
+
+        let results = self.effects.evaluate(&values)?;
+        let effects = to_effects(&results);
+        if choices.is_empty() && effects == EventEffects::default() {
+            return None;
+        }
+        let text_values = [&values[..], &results[..]].concat();
+
+        Some(GameEvent {
+            title: self.title.render(&text_values)?,
+            description: self.description.render(&text_values)?,
+            effects,
+            choices,
+        })
+    }
+}
+
+fn variables(state: &GameState, roll: &EventRoll) -> Vec<i64> {
+    vec![
+        roll.food as i64,
+        roll.gold as i64,
+        roll.citizens as i64,
+        roll.soldiers as i64,
+        roll.farms as i64,
+        state.gold as i64,
+        state.food_quantity as i64,
+        state.man_quantity as i64,
+        state.soldier_quantity as i64,
+        state.weapon_quantity as i64,
+        state.iron_quantity as i64,
+        state.armor_quantity as i64,
+        state.farm_quantity as i64,
+        state.mine_quantity as i64,
+        state.smithy_quantity as i64,
+        state.market_quantity as i64,
+        state.castle_level as i64,
+        state.popularity_percent as i64,
+        state.grade as i64,
+        state.year as i64,
+        state.round as i64,
+    ]
+}
+
+fn to_effects(results: &[i64; 7]) -> EventEffects {
+    let clamp = |value: i64, min: i64, max: i64| value.clamp(min, max);
+    EventEffects {
+        gold_change: clamp(results[0], i32::MIN as i64, i32::MAX as i64) as i32,
+        food_change: clamp(results[1], i32::MIN as i64, i32::MAX as i64) as i32,
+        population_change: clamp(results[2], i32::MIN as i64, i32::MAX as i64) as i32,
+        popularity_change: clamp(results[3], -100, 100) as i8,
+        soldier_change: clamp(results[4], i16::MIN as i64, i16::MAX as i64) as i16,
+        weapon_change: clamp(results[5], i16::MIN as i64, i16::MAX as i64) as i16,
+        farm_change: clamp(results[6], i16::MIN as i64, i16::MAX as i64) as i16,
+        morale_change: 0,
+        battle: None,
+    }
+}
+
+/// The effect formulas of an event or choice, by position in `EFFECTS`
+#[derive(Debug)]
+struct Effects([Option<Expr>; 7]);
+
+impl Effects {
+    fn parse(formulas: &EffectFormulas) -> Result<Self, (&'static str, String)> {
+        let mut effects: [Option<Expr>; 7] = Default::default();
+        for ((effect, name), formula) in effects.iter_mut().zip(EFFECTS).zip(formulas.formulas()) {
+            if let Some(formula) = formula {
+                *effect = Some(Expr::parse(formula, VARIABLES.len()).map_err(|r| (name, r))?);
+            }
+        }
+        Ok(Self(effects))
+    }
+
+    fn evaluate(&self, values: &[i64]) -> Option<[i64; 7]> {
+        let mut results = [0; 7];
+        for (result, effect) in results.iter_mut().zip(&self.0) {
+            if let Some(expr) = effect {
+                *result = expr.evaluate(values)?;
+            }
+        }
+        Some(results)
+    }
+}
+
+/// A text with `{formula}` placeholders
+#[derive(Debug)]
+struct Text(Vec<Segment>);
+
+#[derive(Debug)]
+enum Segment {
+    Literal(String),
+    Value(Expr),
+}
+
+impl Text {
+    fn parse(source: &str) -> Result<Self, String> {
+        let mut segments = Vec::new();
+        let mut rest = source;
+        while let Some(open) = rest.find(['{', '}']) {
+            if rest[open..].starts_with('}') {
+                return Err("has a '}' without a '{'".to_string());
+            }
+            let close = rest[open..]
+                .find('}')
+                .ok_or_else(|| "has a '{' without a '}'".to_string())?
+                + open;
+            if open > 0 {
+                segments.push(Segment::Literal(rest[..open].to_string()));
+            }
+            let formula = &rest[open + 1..close];
+            let expr = Expr::parse(formula, VARIABLES.len() + EFFECTS.len())
+                .map_err(|reason| format!("{{{}}}: {}", formula, reason))?;
+            segments.push(Segment::Value(expr));
+            rest = &rest[close + 1..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Literal(rest.to_string()));
+        }
+        Ok(Self(segments))
+    }
+
+    fn render(&self, values: &[i64]) -> Option<String> {
+        let mut text = String::new();
+        for segment in &self.0 {
+            match segment {
+                Segment::Literal(literal) => text.push_str(literal),
+                Segment::Value(expr) => text.push_str(&expr.evaluate(values)?.to_string()),
+            }
+        }
+        Some(text)
+    }
+}
+
+/// An integer formula: numbers, variables, `+ - * / %`, parentheses, `min` and `max`
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Number(i64),
+    Variable(usize),
+    Negate(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+    Min(Box<Expr>, Box<Expr>),
+    Max(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Parses `source`, allowing the first `variables` names of `VARIABLES` and then
+    /// `EFFECTS`
+    fn parse(source: &str, variables: usize) -> Result<Self, String> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            variables,
+        };
+        let expr = parser.sum()?;
+        match parser.tokens.get(parser.position) {
+            None => Ok(expr),
+            Some(token) => Err(format!("unexpected {}", token)),
+        }
+    }
+
+    /// `None` on a division by zero or an overflow
+    fn evaluate(&self, values: &[i64]) -> Option<i64> {
+        match self {
+            Expr::Number(number) => Some(*number),
+            Expr::Variable(index) => values.get(*index).copied(),
+            Expr::Negate(expr) => expr.evaluate(values)?.checked_neg(),
+            Expr::Binary(op, left, right) => {
+                let (left, right) = (left.evaluate(values)?, right.evaluate(values)?);
+                match op {
+                    '+' => left.checked_add(right),
+                    '-' => left.checked_sub(right),
+                    '*' => left.checked_mul(right),
+                    '/' => left.checked_div(right),
+                    _ => left.checked_rem(right),
+                }
+            }
+            Expr::Min(a, b) => Some(a.evaluate(values)?.min(b.evaluate(values)?)),
+            Expr::Max(a, b) => Some(a.evaluate(values)?.max(b.evaluate(values)?)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(i64),
+    Name(String),
+    Symbol(char),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(number) => write!(f, "'{}'", number),
+            Token::Name(name) => write!(f, "'{}'", name),
+            Token::Symbol(symbol) => write!(f, "'{}'", symbol),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() {
+            let mut number = String::new();
+            while let Some(&digit) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                number.push(digit);
+                chars.next();
+            }
+            let value = number
+                .parse()
+                .map_err(|_| format!("number {} is too large", number))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut name = String::new();
+            while let Some(&letter) = chars
+                .peek()
+                .filter(|l| l.is_ascii_alphanumeric() || **l == '_')
+            {
+                name.push(letter);
+                chars.next();
+            }
+            tokens.push(Token::Name(name));
+        } else if "+-*/%(),".contains(c) {
+            tokens.push(Token::Symbol(c));
+            chars.next();
+        } else {
+            return Err(format!("unexpected '{}'", c));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    variables: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, symbol: char) -> bool {
+        if self.tokens.get(self.position) == Some(&Token::Symbol(symbol)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), String> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            Err(format!("expected '{}'", symbol))
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut expr = self.product()?;
+        loop {
+            let op = if self.eat('+') {
+                '+'
+            } else if self.eat('-') {
+                '-'
+            } else {
+                return Ok(expr);
+            };
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        loop {
+            let op = if self.eat('*') {
+                '*'
+            } else if self.eat('/') {
+                '/'
+            } else if self.eat('%') {
+                '%'
+            } else {
+                return Ok(expr);
+            };
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat('-') {
+            return Ok(Expr::Negate(Box::new(self.unary()?)));
+        }
+        match self.next() {
+            Some(Token::Number(number)) => Ok(Expr::Number(number)),
+            Some(Token::Symbol('(')) => {
+                let expr = self.sum()?;
+                self.expect(')')?;
+                Ok(expr)
+            }
+            Some(Token::Name(name)) if name == "min" || name == "max" => {
+                self.expect('(')?;
+                let a = Box::new(self.sum()?);
+                self.expect(',')?;
+                let b = Box::new(self.sum()?);
+                self.expect(')')?;
+                Ok(if name == "min" {
+                    Expr::Min(a, b)
+                } else {
+                    Expr::Max(a, b)
+                })
+            }
+            Some(Token::Name(name)) => VARIABLES
+                .iter()
+                .chain(EFFECTS.iter())
+                .take(self.variables)
+                .position(|variable| *variable == name)
+                .map(Expr::Variable)
+                .ok_or_else(|| format!("unknown value '{}'", name)),
+            Some(token) => Err(format!("unexpected {}", token)),
+            None => Err("formula ends too early".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::state::{Difficulty, Gender};
+
+    fn roll() -> EventRoll {
+        EventRoll {
+            food: 300,
+            gold: 120,
+            citizens: 20,
+            soldiers: 4,
+            farms: 2,
+        }
+    }
+
+    #[test]
+    fn test_formulas() {
+        let values = [10, 3];
+        let eval = |source: &str| Expr::parse(source, 2).unwrap().evaluate(&values);
+        assert_eq!(eval("food_param + gold_param * 2"), Some(16));
+        assert_eq!(eval("(food_param + gold_param) * 2"), Some(26));
+        assert_eq!(eval("-min(food_param, 4) % 3"), Some(-1));
+        assert_eq!(eval("max(1, gold_param) / 0"), None);
+        assert!(Expr::parse("gold", 2).is_err());
+        assert!(Expr::parse("food_param +", 2).is_err());
+        assert!(Expr::parse("food_param $ 2", 2).is_err());
+    }
+
+    #[test]
+    fn test_event_file() {
+        let event = EventFile::from_toml(
+            r#"
+            id = "tax_feast"
+            title = "Tax Collector's Feast"
+            description = "The tax collectors eat {-food_change} rations in year {year}."
+
+            [conditions]
+            min_grade = 0
+
+            [effects]
+            food_change = "-min(food_param / 2, food)"
+            "#,
+        )
+        .unwrap();
+        let definition = event.compile().unwrap();
+        let state = GameState::with_seed("Test".to_string(), Gender::Male, Difficulty::Easy, 1);
+        let built = definition.build.build(&state, &roll()).unwrap();
+        assert_eq!(built.effects.food_change, -150);
+        assert_eq!(
+            built.description,
+            format!("The tax collectors eat 150 rations in year {}.", state.year)
+        );
+
+        let bad = EventFile {
+            description: "Oops {food_chnage}".to_string(),
+            ..event.clone()
+        };
+        assert_eq!(bad.compile().unwrap_err().0, "description");
+        let empty = EventFile {
+            effects: EffectFormulas::default(),
+            ..event
+        };
+        assert_eq!(empty.compile().unwrap_err().0, "effects");
+    }
+
+    #[test]
+    fn test_shipped_events_load() {
+        let definitions = load_dir("events").unwrap();
+        assert!(!definitions.is_empty());
+    }
+}
@@ -1,14 +1,31 @@
+use crate::game::balance::{self, EventParameter};
+use crate::game::battle::BattleKind;
+use crate::game::event_data::EventTemplate;
 use crate::game::state::GameState;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GameEvent {
     pub title: String,
     pub description: String,
     pub effects: EventEffects,
+    /// When not empty, the player decides what happens and `effects` is ignored
+    #[serde(default)]
+    pub choices: Vec<EventChoice>,
 }
 
-#[derive(Debug, Clone, Default)]
+/// One way the player can answer a decision event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventChoice {
+    pub label: String,
+    /// What happened, shown once the choice is made
+    pub outcome: String,
+    pub effects: EventEffects,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct EventEffects {
     pub gold_change: i32,
     pub food_change: i32,
@@ -17,79 +34,340 @@ pub struct EventEffects {
     pub soldier_change: i16,
     pub weapon_change: i16,
     pub farm_change: i16,
+    #[serde(default)]
+    pub morale_change: i8,
+    /// A battle fought after the other effects; see `battle::fight`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub battle: Option<BattleKind>,
+}
+
+/// The amounts an event works with, rolled once before the event is picked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventRoll {
+    pub food: i32,
+    pub gold: i32,
+    pub citizens: i16,
+    pub soldiers: i16,
+    pub farms: i16,
+}
+
+impl EventRoll {
+    /// Rolls the amounts as in GameCanvas.ShowRandomEvent(), with the percentages and
+    /// bases taken from the balance (originally):
+    /// - food: random(foodQuantity * 30 / 100) + 200
+    /// - gold: random(gold * 40 / 100) + 50
+    /// - citizens: random(manQuantity * 9 / 100) + 5
+    /// - soldiers: random(soldierQuantity * 10 / 100) + 2
+    /// - farms: 2 to 4
+    pub fn roll<R: Rng + ?Sized>(state: &GameState, rng: &mut R) -> Self {
+        let events = &balance::current().events;
+        let mut roll = |value: i32, parameter: EventParameter| {
+            rng.gen_range(0..(value * parameter.percent / 100).max(1)) + parameter.base
+        };
+
+        let food = roll(state.food_quantity, events.food);
+        let gold = roll(state.gold, events.gold);
+        let citizens = roll(state.man_quantity, events.citizens) as i16;
+        let soldiers = events.soldiers;
+        let soldier_range = (state.soldier_quantity as i32 * soldiers.percent / 100).max(1);
+        let soldiers = rng.gen_range(0..soldier_range as i16) + soldiers.base as i16;
+        let farms = rng.gen_range(events.farms_min as i32..=events.farms_max as i32) as i16;
+
+        Self {
+            food,
+            gold,
+            citizens,
+            soldiers,
+            farms,
+        }
+    }
+}
+
+/// What the country must look like for an event to happen
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EventConditions {
+    pub min_soldiers: i16,
+    pub max_soldiers: Option<i16>,
+    pub min_markets: i16,
+    pub min_grade: u8,
+    pub max_grade: Option<u8>,
+    pub first_year: Option<u16>,
+    pub last_year: Option<u16>,
+}
+
+impl EventConditions {
+    pub fn allow(&self, state: &GameState) -> bool {
+        state.soldier_quantity >= self.min_soldiers
+            && self
+                .max_soldiers
+                .is_none_or(|max| state.soldier_quantity <= max)
+            && state.market_quantity >= self.min_markets
+            && state.grade >= self.min_grade
+            && self.max_grade.is_none_or(|max| state.grade <= max)
+            && self.first_year.is_none_or(|first| state.year >= first)
+            && self.last_year.is_none_or(|last| state.year <= last)
+    }
+}
+
+/// Builds the event from the rolled amounts, or `None` when it makes no sense right now
+pub type EventFn = fn(&GameState, &EventRoll) -> Option<GameEvent>;
+
+/// Where an event comes from: a function in this file or an event data file
+#[derive(Debug, Clone)]
+pub enum EventBuilder {
+    Code(EventFn),
+    Data(Arc<EventTemplate>),
+}
+
+impl EventBuilder {
+    pub fn build(&self, state: &GameState, roll: &EventRoll) -> Option<GameEvent> {
+        match self {
+            EventBuilder::Code(build) => build(state, roll),
+            EventBuilder::Data(template) => template.build(state, roll),
+        }
+    }
+}
+
+/// One kind of event the registry can pick
+#[derive(Debug, Clone)]
+pub struct EventDefinition {
+    /// Stable name, used to track the event in saved games
+    pub id: String,
+    /// Relative chance against the other events that can happen
+    pub weight: u32,
+    pub conditions: EventConditions,
+    /// Rounds that must pass before the event can happen again
+    pub cooldown: u16,
+    /// How often the event can happen in one game; `None` is unlimited
+    pub max_occurrences: Option<u16>,
+    pub build: EventBuilder,
+}
+
+impl EventDefinition {
+    pub const DEFAULT_WEIGHT: u32 = 10;
+
+    pub fn new(id: &str, build: EventFn) -> Self {
+        Self {
+            id: id.to_string(),
+            weight: Self::DEFAULT_WEIGHT,
+            conditions: EventConditions::default(),
+            cooldown: 0,
+            max_occurrences: None,
+            build: EventBuilder::Code(build),
+        }
+    }
+
+    /// Whether the conditions hold and the cooldown and occurrence limit allow it
+    pub fn is_available(&self, state: &GameState) -> bool {
+        if self.weight == 0 || !self.conditions.allow(state) {
+            return false;
+        }
+        match state.event_history.get(&self.id) {
+            None => true,
+            Some(record) => {
+                state.round.saturating_sub(record.last_round) > self.cooldown
+                    && self
+                        .max_occurrences
+                        .is_none_or(|max| record.occurrences < max)
+            }
+        }
+    }
+}
+
+/// How often an event happened in a game, and when it last did
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventRecord {
+    pub occurrences: u16,
+    pub last_round: u16,
+}
+
+/// The events that can happen, picked by weight among those available
+#[derive(Debug, Clone, Default)]
+pub struct EventRegistry {
+    definitions: Vec<EventDefinition>,
+}
+
+static REGISTRY: OnceLock<EventRegistry> = OnceLock::new();
+
+/// The registry in use: the standard events unless another one was installed
+pub fn registry() -> &'static EventRegistry {
+    REGISTRY.get_or_init(EventRegistry::standard)
+}
+
+/// Makes `registry` the one used by every game. Only the first call has any effect, and
+/// it must happen before the first event is generated.
+pub fn install(registry: EventRegistry) -> bool {
+    REGISTRY.set(registry).is_ok()
+}
+
+impl EventRegistry {
+    /// The events of the original game. Events that used to redirect to another event
+    /// (pay soldiers to deserters, thieves to caught thieves, battle casualties to the Dark
+    /// Emperor's attack) now have conditions instead, and the other event is picked on
+    /// its own.
+    pub fn standard() -> Self {
+        let mut registry = Self::default();
+        let soldiers = |min_soldiers| EventConditions {
+            min_soldiers,
+            ..Default::default()
+        };
+
+        registry.register(EventDefinition::new(
+            "plague_rats",
+            EventGenerator::event_plague_rats,
+        ));
+        registry.register(EventDefinition::new(
+            "accountant_flees",
+            EventGenerator::event_accountant_flees,
+        ));
+        registry.register(EventDefinition::new(
+            "gold_transporter_robbed",
+            EventGenerator::event_gold_transporter_robbed,
+        ));
+        registry.register(EventDefinition::new(
+            "epidemic",
+            EventGenerator::event_epidemic,
+        ));
+        registry.register(EventDefinition {
+            cooldown: 2,
+            ..EventDefinition::new("refugees", EventGenerator::event_refugees)
+        });
+        registry.register(EventDefinition {
+            cooldown: 3,
+            ..EventDefinition::new("fire_farms", EventGenerator::event_fire_farms)
+        });
+        registry.register(EventDefinition::new(
+            "thieves_plundering",
+            EventGenerator::event_thieves_plundering,
+        ));
+        registry.register(EventDefinition {
+            conditions: soldiers(3),
+            ..EventDefinition::new("weapons_found", EventGenerator::event_weapons_found)
+        });
+        registry.register(EventDefinition {
+            conditions: soldiers(2),
+            ..EventDefinition::new("soldiers_flee", EventGenerator::event_soldiers_flee)
+        });
+        registry.register(EventDefinition::new(
+            "joyous_festival",
+            EventGenerator::event_joyous_festival,
+        ));
+        registry.register(EventDefinition::new(
+            "warehouse_fire",
+            EventGenerator::event_warehouse_fire,
+        ));
+        registry.register(EventDefinition {
+            conditions: soldiers(2),
+            ..EventDefinition::new("pay_soldiers", EventGenerator::event_pay_soldiers)
+        });
+        registry.register(EventDefinition {
+            conditions: EventConditions {
+                max_soldiers: Some(100),
+                ..Default::default()
+            },
+            ..EventDefinition::new(
+                "thieves_steal_gold",
+                EventGenerator::event_thieves_steal_gold,
+            )
+        });
+        registry.register(EventDefinition::new(
+            "give_to_poor",
+            EventGenerator::event_give_to_poor,
+        ));
+        registry.register(EventDefinition::new(
+            "warehouse_renovation",
+            EventGenerator::event_warehouse_renovation,
+        ));
+        registry.register(EventDefinition {
+            conditions: soldiers(10),
+            ..EventDefinition::new("battle_casualties", EventGenerator::event_battle_casualties)
+        });
+        registry.register(EventDefinition {
+            cooldown: 2,
+            ..EventDefinition::new(
+                "dark_emperor_attack",
+                EventGenerator::event_dark_emperor_attack,
+            )
+        });
+        registry.register(EventDefinition {
+            conditions: soldiers(1),
+            ..EventDefinition::new("catch_thieves", EventGenerator::event_catch_thieves)
+        });
+
+        registry
+    }
+
+    /// Adds an event, replacing any earlier definition with the same id
+    pub fn register(&mut self, definition: EventDefinition) {
+        match self.definitions.iter_mut().find(|d| d.id == definition.id) {
+            Some(existing) => *existing = definition,
+            None => self.definitions.push(definition),
+        }
+    }
+
+    pub fn definitions(&self) -> &[EventDefinition] {
+        &self.definitions
+    }
+
+    pub fn get(&self, id: &str) -> Option<&EventDefinition> {
+        self.definitions.iter().find(|d| d.id == id)
+    }
+
+    /// Picks one of the available events by weight and builds it. Returns the id of the
+    /// picked definition with the event, or `None` when nothing is available or the
+    /// picked event does not fit the current state.
+    pub fn pick<R: Rng + ?Sized>(
+        &self,
+        state: &GameState,
+        rng: &mut R,
+    ) -> Option<(&str, GameEvent)> {
+        let roll = EventRoll::roll(state, rng);
+        let candidates: Vec<&EventDefinition> = self
+            .definitions
+            .iter()
+            .filter(|d| d.is_available(state))
+            .collect();
+
+        let total: u32 = candidates.iter().map(|d| d.weight).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut target = rng.gen_range(0..total);
+        let definition = candidates
+            .iter()
+            .find(|d| {
+                if target < d.weight {
+                    true
+                } else {
+                    target -= d.weight;
+                    false
+                }
+            })
+            .unwrap_or(&candidates[candidates.len() - 1]);
+        let event = definition.build.build(state, &roll)?;
+        Some((definition.id.as_str(), event))
+    }
 }
 
 pub struct EventGenerator;
 
 impl EventGenerator {
-    /// Generates a random event based on the original game logic
-    /// Parameters are calculated as in GameCanvas.ShowRandomEvent():
-    /// - food_param: random(foodQuantity * 30 / 100) + 200
-    /// - gold_param: random(gold * 40 / 100) + 50
-    /// - man_param: random(manQuantity * 9 / 100) + 5
-    /// - soldier_param: random(soldierQuantity * 10 / 100) + 2
-    /// - farm_param: random(2) + 2
-    pub fn generate_random_event(state: &GameState) -> Option<GameEvent> {
-        let mut rng = rand::thread_rng();
-
-        // Calculate parameters as in original game
-        let food_param = rng.gen_range(0..(state.food_quantity * 30 / 100).max(1)) + 200;
-        let gold_param = rng.gen_range(0..(state.gold * 40 / 100).max(1)) + 50;
-        let man_param = (rng.gen_range(0..(state.man_quantity * 9 / 100).max(1)) + 5) as i16;
-        let soldier_param =
-            (rng.gen_range(0..(state.soldier_quantity * 10 / 100).max(1)) + 2) as i16;
-        let farm_param = (rng.gen_range(0..3) + 2) as i16;
-
-        // Choose random event (0-18, but we use 0-15 to avoid some edge cases)
-        let event_id = rng.gen_range(0..19);
-
-        Self::generate_event(
-            event_id,
-            state,
-            farm_param,
-            man_param,
-            soldier_param,
-            food_param,
-            gold_param,
-        )
-    }
-
-    fn generate_event(
-        event_id: usize,
-        state: &GameState,
-        farm_param: i16,
-        man_param: i16,
-        soldier_param: i16,
-        food_param: i32,
-        gold_param: i32,
+    /// Picks a random event from the installed registry and records it in the game's
+    /// event history
+    pub fn generate_random_event<R: Rng + ?Sized>(
+        state: &mut GameState,
+        rng: &mut R,
     ) -> Option<GameEvent> {
-        match event_id {
-            0 => Self::event_plague_rats(state, food_param),
-            1 => Self::event_accountant_flees(state, gold_param),
-            2 => Self::event_gold_transporter_robbed(state, gold_param),
-            3 => Self::event_epidemic(state, man_param),
-            4 => Self::event_refugees(state, man_param),
-            5 => Self::event_fire_farms(state, farm_param),
-            6 => Self::event_thieves_plundering(state, man_param),
-            7 => Self::event_weapons_found(state, soldier_param),
-            8 => Self::event_soldiers_flee(state, soldier_param),
-            9 => Self::event_joyous_festival(state, gold_param),
-            10 => Self::event_warehouse_fire(state, food_param),
-            11 => Self::event_pay_soldiers(state),
-            12 => Self::event_thieves_steal_gold(state, gold_param, man_param, soldier_param),
-            13 => Self::event_give_to_poor(state, gold_param),
-            14 => Self::event_warehouse_renovation(state, gold_param),
-            15 => Self::event_battle_casualties(state, soldier_param, man_param),
-            16 => Self::event_deserters(state, soldier_param),
-            17 => Self::event_dark_emperor_attack(state, man_param),
-            18 => Self::event_catch_thieves(state, man_param),
-            _ => None,
-        }
+        let (id, event) = registry().pick(state, rng)?;
+        state.record_event(id);
+        Some(event)
     }
 
     // Event 0: A plague of rats causes you to lose * food rations
-    fn event_plague_rats(state: &GameState, food_param: i32) -> Option<GameEvent> {
-        let food_loss = food_param.min(state.food_quantity);
+    fn event_plague_rats(state: &GameState, roll: &EventRoll) -> Option<GameEvent> {
+        let food_loss = roll.food.min(state.food_quantity);
 
         if food_loss < 3 {
             return None; // Not enough food to lose
@@ -105,12 +383,13 @@ impl EventGenerator {
                 food_change: -food_loss,
                 ..Default::default()
             },
+            choices: Vec::new(),
         })
     }
 
     // Event 1: One of your accountants takes * pieces of gold and flees
-    fn event_accountant_flees(state: &GameState, gold_param: i32) -> Option<GameEvent> {
-        let gold_loss = gold_param.min(state.gold);
+    fn event_accountant_flees(state: &GameState, roll: &EventRoll) -> Option<GameEvent> {
+        let gold_loss = roll.gold.min(state.gold);
 
         if gold_loss == 0 {
             return None;
@@ -126,12 +405,13 @@ impl EventGenerator {
                 gold_change: -gold_loss,
                 ..Default::default()
             },
+            choices: Vec::new(),
         })
     }
 
     // Event 2: One of your gold transporter is robbed and * pieces of gold are stolen
-    fn event_gold_transporter_robbed(state: &GameState, gold_param: i32) -> Option<GameEvent> {
-        let gold_loss = gold_param.min(state.gold);
+    fn event_gold_transporter_robbed(state: &GameState, roll: &EventRoll) -> Option<GameEvent> {
+        let gold_loss = roll.gold.min(state.gold);
 
         if gold_loss == 0 {
             return None;
@@ -147,16 +427,17 @@ impl EventGenerator {
                 gold_change: -gold_loss,
                 ..Default::default()
             },
+            choices: Vec::new(),
         })
     }
 
     // Event 3: An epidemic kills * inhabitants
-    fn event_epidemic(state: &GameState, man_param: i16) -> Option<GameEvent> {
-        if state.man_quantity < 10 || man_param < 10 {
+    fn event_epidemic(state: &GameState, roll: &EventRoll) -> Option<GameEvent> {
+        if state.man_quantity < 10 || roll.citizens < 10 {
             return None;
         }
 
-        let population_loss = man_param.min(state.man_quantity as i16);
+        let population_loss = roll.citizens.min(state.man_quantity as i16);
 
         Some(GameEvent {
             title: "Epidemic".to_string(),
@@ -165,24 +446,48 @@ impl EventGenerator {
                 population_change: -(population_loss as i32),
                 ..Default::default()
             },
+            choices: Vec::new(),
         })
     }
 
     // Event 4: A wave of * refugees arrives in your country
-    fn event_refugees(_state: &GameState, man_param: i16) -> Option<GameEvent> {
+    // Taking them in costs two rations each; turning them away angers the people.
+    fn event_refugees(_state: &GameState, roll: &EventRoll) -> Option<GameEvent> {
+        let food_cost = roll.citizens as i32 * 2;
+
         Some(GameEvent {
             title: "Refugees Arrive".to_string(),
-            description: format!("A wave of {} refugees arrives in your country.", man_param),
-            effects: EventEffects {
-                population_change: man_param as i32,
-                ..Default::default()
-            },
+            description: format!(
+                "A wave of {} refugees arrives in your country. Will you take them in?",
+                roll.citizens
+            ),
+            effects: EventEffects::default(),
+            choices: vec![
+                EventChoice {
+                    label: format!("Accept them ({} food)", food_cost),
+                    outcome: format!("{} refugees settle in your country.", roll.citizens),
+                    effects: EventEffects {
+                        population_change: roll.citizens as i32,
+                        food_change: -food_cost,
+                        ..Default::default()
+                    },
+                },
+                EventChoice {
+                    label: "Turn them away".to_string(),
+                    outcome: "The refugees are turned away. Your people think you heartless."
+                        .to_string(),
+                    effects: EventEffects {
+                        popularity_change: -3,
+                        ..Default::default()
+                    },
+                },
+            ],
         })
     }
 
     // Event 5: Because of a major fire * farms are destroyed
-    fn event_fire_farms(state: &GameState, farm_param: i16) -> Option<GameEvent> {
-        if farm_param > state.farm_quantity {
+    fn event_fire_farms(state: &GameState, roll: &EventRoll) -> Option<GameEvent> {
+        if roll.farms > state.farm_quantity {
             return None;
         }
 
@@ -190,20 +495,21 @@ impl EventGenerator {
             title: "Major Fire".to_string(),
             description: format!(
                 "Because of a major fire {} farms are destroyed.",
-                farm_param
+                roll.farms
             ),
             effects: EventEffects {
-                farm_change: -farm_param,
+                farm_change: -roll.farms,
                 ..Default::default()
             },
+            choices: Vec::new(),
         })
     }
 
     // Event 6: Thieves are plundering your villages. Your people require more protection.
-    fn event_thieves_plundering(state: &GameState, man_param: i16) -> Option<GameEvent> {
-        // If enough soldiers (soldierQuantity * 50 >= manQuantity), redirect to event 18
-        if state.soldier_quantity * 50 >= state.man_quantity as i16 {
-            return Self::event_catch_thieves(state, man_param);
+    // Only happens while the army is too small to protect the people
+    fn event_thieves_plundering(state: &GameState, _roll: &EventRoll) -> Option<GameEvent> {
+        if state.soldier_quantity as i32 * 50 >= state.man_quantity {
+            return None;
         }
 
         Some(GameEvent {
@@ -215,49 +521,43 @@ impl EventGenerator {
                 popularity_change: -8,
                 ..Default::default()
             },
+            choices: Vec::new(),
         })
     }
 
     // Event 7: In a secret hiding place your soldiers find * weapons
-    fn event_weapons_found(state: &GameState, soldier_param: i16) -> Option<GameEvent> {
-        if state.soldier_quantity <= 2 {
-            return None;
-        }
-
+    fn event_weapons_found(_state: &GameState, roll: &EventRoll) -> Option<GameEvent> {
         Some(GameEvent {
             title: "Weapons Found".to_string(),
             description: format!(
                 "In a secret hiding place your soldiers find {} weapons.",
-                soldier_param
+                roll.soldiers
             ),
             effects: EventEffects {
-                weapon_change: soldier_param,
+                weapon_change: roll.soldiers,
                 ..Default::default()
             },
+            choices: Vec::new(),
         })
     }
 
     // Event 8: According to rumor the Dark Emperor controls an enormous army. * Of your soldiers flee.
-    fn event_soldiers_flee(state: &GameState, soldier_param: i16) -> Option<GameEvent> {
-        if state.soldier_quantity < 2 || soldier_param < 2 {
-            return None;
-        }
-
-        let soldier_loss = soldier_param.min(state.soldier_quantity);
-
+    // The rumor now lowers morale; whether soldiers flee is up to the round's desertion step.
+    fn event_soldiers_flee(_state: &GameState, _roll: &EventRoll) -> Option<GameEvent> {
         Some(GameEvent {
-            title: "Soldiers Flee".to_string(),
-            description: format!("According to rumor the Dark Emperor controls an enormous army. {} of your soldiers flee.", soldier_loss),
+            title: "Rumors of War".to_string(),
+            description: "According to rumor the Dark Emperor controls an enormous army. Your soldiers lose heart.".to_string(),
             effects: EventEffects {
-                soldier_change: -soldier_loss,
+                morale_change: -10,
                 ..Default::default()
             },
+            choices: Vec::new(),
         })
     }
 
     // Event 9: You celebrate a joyous festival for * pieces of gold
-    fn event_joyous_festival(state: &GameState, gold_param: i32) -> Option<GameEvent> {
-        let gold_cost = gold_param.min(state.gold);
+    fn event_joyous_festival(state: &GameState, roll: &EventRoll) -> Option<GameEvent> {
+        let gold_cost = roll.gold.min(state.gold);
 
         if gold_cost == 0 {
             return None;
@@ -273,12 +573,13 @@ impl EventGenerator {
                 gold_change: -gold_cost,
                 ..Default::default()
             },
+            choices: Vec::new(),
         })
     }
 
     // Event 10: Fire in your warehouse. * food rations burn
-    fn event_warehouse_fire(state: &GameState, food_param: i32) -> Option<GameEvent> {
-        let food_loss = food_param.min(state.food_quantity);
+    fn event_warehouse_fire(state: &GameState, roll: &EventRoll) -> Option<GameEvent> {
+        let food_loss = roll.food.min(state.food_quantity);
 
         if food_loss < 3 {
             return None;
@@ -291,46 +592,34 @@ impl EventGenerator {
                 food_change: -food_loss,
                 ..Default::default()
             },
+            choices: Vec::new(),
         })
     }
 
     // Event 11: To increase moral, you pay 10 pieces of gold to each soldier
-    fn event_pay_soldiers(state: &GameState) -> Option<GameEvent> {
-        if state.soldier_quantity < 2 {
-            return None;
-        }
-
+    fn event_pay_soldiers(state: &GameState, _roll: &EventRoll) -> Option<GameEvent> {
         let total_cost = state.soldier_quantity as i32 * 10;
 
-        // If not enough gold, redirect to event 16 (deserters)
         if total_cost >= state.gold {
-            let soldier_param = (state.soldier_quantity * 10 / 100).max(2);
-            return Self::event_deserters(state, soldier_param);
+            return None;
         }
 
         Some(GameEvent {
             title: "Pay Soldiers".to_string(),
-            description: format!("To increase moral, you pay 10 pieces of gold to each soldier."),
+            description: "To increase morale, you pay 10 pieces of gold to each soldier."
+                .to_string(),
             effects: EventEffects {
                 gold_change: -total_cost,
+                morale_change: 10,
                 ..Default::default()
             },
+            choices: Vec::new(),
         })
     }
 
     // Event 12: Thieves steal * pieces of gold out of your treasure chamber
-    fn event_thieves_steal_gold(
-        state: &GameState,
-        gold_param: i32,
-        man_param: i16,
-        _soldier_param: i16,
-    ) -> Option<GameEvent> {
-        // If enough soldiers (> 100), redirect to event 18
-        if state.soldier_quantity > 100 {
-            return Self::event_catch_thieves(state, man_param);
-        }
-
-        let gold_loss = gold_param.min(state.gold);
+    fn event_thieves_steal_gold(state: &GameState, roll: &EventRoll) -> Option<GameEvent> {
+        let gold_loss = roll.gold.min(state.gold);
 
         if gold_loss == 0 {
             return None;
@@ -346,16 +635,17 @@ impl EventGenerator {
                 gold_change: -gold_loss,
                 ..Default::default()
             },
+            choices: Vec::new(),
         })
     }
 
     // Event 13: To increase your popularity, you give out * pieces of gold to the poor
-    fn event_give_to_poor(state: &GameState, gold_param: i32) -> Option<GameEvent> {
+    fn event_give_to_poor(state: &GameState, roll: &EventRoll) -> Option<GameEvent> {
         if state.gold < 10 || state.man_quantity < 10 {
             return None;
         }
 
-        let gold_given = gold_param.min(state.gold);
+        let gold_given = roll.gold.min(state.gold);
         let popularity_gain = if gold_given < 500 { 2 } else { 5 };
 
         Some(GameEvent {
@@ -369,12 +659,13 @@ impl EventGenerator {
                 popularity_change: popularity_gain,
                 ..Default::default()
             },
+            choices: Vec::new(),
         })
     }
 
     // Event 14: Your warehouse must be renovated for * pieces of gold
-    fn event_warehouse_renovation(state: &GameState, gold_param: i32) -> Option<GameEvent> {
-        let gold_cost = gold_param.min(state.gold);
+    fn event_warehouse_renovation(state: &GameState, roll: &EventRoll) -> Option<GameEvent> {
+        let gold_cost = roll.gold.min(state.gold);
 
         if gold_cost == 0 {
             return None;
@@ -390,79 +681,75 @@ impl EventGenerator {
                 gold_change: -gold_cost,
                 ..Default::default()
             },
+            choices: Vec::new(),
         })
     }
 
-    // Event 15: At a fight against the Dark Emperor's army, * soldiers are killed
-    fn event_battle_casualties(
-        state: &GameState,
-        soldier_param: i16,
-        man_param: i16,
-    ) -> Option<GameEvent> {
-        // If not enough soldiers (< 10), redirect to event 17
-        if state.soldier_quantity < 10 {
-            return Self::event_dark_emperor_attack(state, man_param);
-        }
-
-        let soldier_loss = soldier_param.min(state.soldier_quantity);
-
+    // Event 15: At a fight against the Dark Emperor's army, * soldiers are killed.
+    // Fought out as a battle, see `battle::fight`.
+    fn event_battle_casualties(_state: &GameState, _roll: &EventRoll) -> Option<GameEvent> {
         Some(GameEvent {
-            title: "Battle Casualties".to_string(),
-            description: format!(
-                "At a fight against the Dark Emperor's army, {} soldiers are killed.",
-                soldier_loss
-            ),
+            title: "Border Battle".to_string(),
+            description: "The Dark Emperor's army attacks your border.".to_string(),
             effects: EventEffects {
-                soldier_change: -soldier_loss,
-                weapon_change: -soldier_loss, // Also lose weapons (based on gameStorage.b() call)
+                battle: Some(BattleKind::Defense),
                 ..Default::default()
             },
+            choices: Vec::new(),
         })
     }
 
-    // Event 16: * of your soldiers desert the army
-    fn event_deserters(state: &GameState, soldier_param: i16) -> Option<GameEvent> {
-        if state.soldier_quantity < 2 || soldier_param < 2 {
+    // Event 16: * of your soldiers desert the army. Now the round's desertion step, driven
+    // by morale; see `army`.
+
+    // Event 17: The Dark Emperor's soldiers kill * inhabitants. Your people are terrified.
+    // He first demands a tribute; if it is refused, a battle decides.
+    fn event_dark_emperor_attack(state: &GameState, roll: &EventRoll) -> Option<GameEvent> {
+        if state.man_quantity < 2 || roll.citizens < 2 {
             return None;
         }
 
-        let deserters = soldier_param.min(state.soldier_quantity);
-
-        Some(GameEvent {
-            title: "Deserters".to_string(),
-            description: format!("{} of your soldiers desert the army.", deserters),
+        let attack = EventChoice {
+            label: "Fight".to_string(),
+            outcome: "You refuse, and your soldiers ride out to meet the Dark Emperor's army."
+                .to_string(),
             effects: EventEffects {
-                soldier_change: -deserters,
+                battle: Some(BattleKind::Defense),
                 ..Default::default()
             },
-        })
-    }
-
-    // Event 17: The Dark Emperor's soldiers kill * inhabitants. Your people are terrified.
-    fn event_dark_emperor_attack(state: &GameState, man_param: i16) -> Option<GameEvent> {
-        if state.man_quantity < 2 || man_param < 2 {
-            return None;
+        };
+
+        let tribute = roll.citizens.min(state.man_quantity as i16) as i32 * 10;
+        let mut choices = vec![attack];
+        if state.gold >= tribute {
+            choices.insert(
+                0,
+                EventChoice {
+                    label: format!("Pay {} gold", tribute),
+                    outcome: format!(
+                        "You pay {} pieces of gold and the Dark Emperor's army moves on.",
+                        tribute
+                    ),
+                    effects: EventEffects {
+                        gold_change: -tribute,
+                        ..Default::default()
+                    },
+                },
+            );
         }
 
-        let population_loss = man_param.min(state.man_quantity as i16);
-
         Some(GameEvent {
             title: "Dark Emperor Attack".to_string(),
-            description: format!(
-                "The Dark Emperor's soldiers kill {} inhabitants. Your people are terrified.",
-                population_loss
-            ),
-            effects: EventEffects {
-                population_change: -(population_loss as i32),
-                popularity_change: -12,
-                ..Default::default()
-            },
+            description: "The Dark Emperor's army stands at your border and demands a tribute."
+                .to_string(),
+            effects: EventEffects::default(),
+            choices,
         })
     }
 
     // Event 18: Your soldiers catch a band of thieves and retrieve * pieces of gold
-    fn event_catch_thieves(_state: &GameState, man_param: i16) -> Option<GameEvent> {
-        let gold_gained = man_param as i32 * 7;
+    fn event_catch_thieves(_state: &GameState, roll: &EventRoll) -> Option<GameEvent> {
+        let gold_gained = roll.citizens as i32 * 7;
 
         Some(GameEvent {
             title: "Thieves Caught".to_string(),
@@ -475,28 +762,110 @@ impl EventGenerator {
                 popularity_change: 3,
                 ..Default::default()
             },
+            choices: Vec::new(),
         })
     }
 }
 
 impl GameEvent {
+    pub fn is_decision(&self) -> bool {
+        !self.choices.is_empty()
+    }
+
     pub fn apply_to_state(&self, state: &mut GameState) {
-        state.change_gold(self.effects.gold_change);
-        state.change_food(self.effects.food_change);
-        state.change_population(self.effects.population_change);
-        state.change_popularity(self.effects.popularity_change);
-        state.change_soldiers(self.effects.soldier_change);
+        self.effects.apply_to_state(state);
+    }
+}
+
+impl EventEffects {
+    pub fn apply_to_state(&self, state: &mut GameState) {
+        state.change_gold(self.gold_change);
+        state.change_food(self.food_change);
+        state.change_population(self.population_change);
+        state.change_popularity(self.popularity_change);
+        state.change_soldiers(self.soldier_change);
+        state.change_morale(self.morale_change);
 
         // Handle farm changes
-        if self.effects.farm_change != 0 {
+        if self.farm_change != 0 {
             state.farm_quantity =
-                (state.farm_quantity as i32 + self.effects.farm_change as i32).max(0) as i16;
+                (state.farm_quantity as i32 + self.farm_change as i32).max(0) as i16;
         }
 
         // Handle weapon changes
-        if self.effects.weapon_change != 0 {
+        if self.weapon_change != 0 {
             state.weapon_quantity =
-                (state.weapon_quantity as i32 + self.effects.weapon_change as i32).max(0) as i16;
+                (state.weapon_quantity as i32 + self.weapon_change as i32).max(0) as i16;
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::state::{Difficulty, Gender};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn game() -> GameState {
+        GameState::with_seed("Test".to_string(), Gender::Male, Difficulty::Easy, 1)
+    }
+
+    #[test]
+    fn test_conditions_and_cooldown() {
+        let mut state = game();
+        let registry = EventRegistry::standard();
+        let battle = registry.get("battle_casualties").unwrap();
+        assert!(!battle.is_available(&state));
+        state.soldier_quantity = 10;
+        assert!(battle.is_available(&state));
+
+        let refugees = registry.get("refugees").unwrap();
+        state.record_event("refugees");
+        state.round += 2;
+        assert!(!refugees.is_available(&state));
+        state.round += 1;
+        assert!(refugees.is_available(&state));
+
+        let limited = EventDefinition {
+            max_occurrences: Some(1),
+            ..refugees.clone()
+        };
+        assert!(!limited.is_available(&state));
+    }
+
+    #[test]
+    fn test_pick_by_weight() {
+        let state = game();
+        let mut registry = EventRegistry::default();
+        let mut rng = StdRng::seed_from_u64(3);
+        assert!(registry.pick(&state, &mut rng).is_none());
+
+        registry.register(EventDefinition::new(
+            "refugees",
+            EventGenerator::event_refugees,
+        ));
+        registry.register(EventDefinition {
+            weight: 0,
+            ..EventDefinition::new("festival", EventGenerator::event_joyous_festival)
+        });
+        for _ in 0..20 {
+            let (id, event) = registry.pick(&state, &mut rng).unwrap();
+            assert_eq!(id, "refugees");
+            assert!(event.is_decision());
+        }
+    }
+
+    #[test]
+    fn test_large_army_keeps_thieves_away() {
+        let mut state = game();
+        state.soldier_quantity = 700;
+        state.man_quantity = 20000;
+        let mut rng = StdRng::seed_from_u64(3);
+        let roll = EventRoll::roll(&state, &mut rng);
+        assert!(EventGenerator::event_thieves_plundering(&state, &roll).is_none());
+
+        state.soldier_quantity = 10;
+        assert!(EventGenerator::event_thieves_plundering(&state, &roll).is_some());
+    }
+}
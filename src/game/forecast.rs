@@ -0,0 +1,331 @@
+//! Round forecast
+//! What finishing the round will do with the current settings, worked out from the round
+//! formulas without rolling any dice. Every random roll gives a band from its lowest to
+//! its highest value, with the expected value in between. Random events are not included.
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::army::Upkeep;
+use crate::game::balance;
+use crate::game::buildings::{Building, Maintenance};
+use crate::game::round;
+use crate::game::state::GameState;
+
+/// Lowest, expected and highest value of a forecast number
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Band {
+    pub min: i32,
+    pub expected: i32,
+    pub max: i32,
+}
+
+impl Band {
+    fn exact(value: i32) -> Self {
+        Self {
+            min: value,
+            expected: value,
+            max: value,
+        }
+    }
+
+    /// A roll from `low` to `high` with both ends included
+    fn roll(low: i32, high: i32) -> Self {
+        Self {
+            min: low,
+            expected: (low + high) / 2,
+            max: high,
+        }
+    }
+
+    /// Applies `f` to each of the three values, keeping `min` the lowest
+    fn map(self, f: impl Fn(i32) -> i32) -> Self {
+        let (a, b) = (f(self.min), f(self.max));
+        Self {
+            min: a.min(b),
+            expected: f(self.expected),
+            max: a.max(b),
+        }
+    }
+
+    fn add(self, other: Band) -> Self {
+        Self {
+            min: self.min + other.min,
+            expected: self.expected + other.expected,
+            max: self.max + other.max,
+        }
+    }
+
+    pub fn is_exact(&self) -> bool {
+        self.min == self.max
+    }
+}
+
+/// How likely the people go hungry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StarvationRisk {
+    /// The food stores are enough this year and, whatever the harvest, the next
+    None,
+    /// A poor harvest would leave too little for next year
+    Possible,
+    /// An average harvest would leave too little for next year
+    Likely,
+    /// The food stores cannot feed everyone this year
+    Certain,
+}
+
+impl StarvationRisk {
+    pub fn label(&self) -> &'static str {
+        match self {
+            StarvationRisk::None => "None",
+            StarvationRisk::Possible => "After a poor harvest",
+            StarvationRisk::Likely => "Likely next year",
+            StarvationRisk::Certain => "This year",
+        }
+    }
+
+    pub fn css_class(&self) -> &'static str {
+        match self {
+            StarvationRisk::None => "risk-none",
+            StarvationRisk::Possible => "risk-possible",
+            StarvationRisk::Likely => "risk-likely",
+            StarvationRisk::Certain => "risk-certain",
+        }
+    }
+}
+
+/// The expected outcome of the round
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Forecast {
+    pub taxes: Band,
+    pub market_income: Band,
+    /// Gold and food the soldiers are owed this round
+    pub wages: i32,
+    pub rations: i32,
+    /// Gold the buildings cost this round
+    pub building_upkeep: i32,
+    /// Gold at the end of the round, after the soldiers and the buildings are paid
+    pub gold: Band,
+    /// Food the people eat at the chosen supply level
+    pub food_needed: i32,
+    /// Food in the stores before the round
+    pub food_stock: i32,
+    pub harvest: Band,
+    /// Food at the end of the round, after eating, the harvest and the soldiers' rations
+    pub food: Band,
+    pub starvation: StarvationRisk,
+    pub popularity_change: Band,
+    pub popularity: Band,
+    pub morale: Band,
+    pub population_change: Band,
+    pub citizens: Band,
+}
+
+impl Forecast {
+    /// Follows the steps of `round::apply_round_effects` in the same order
+    pub fn of(game: &GameState) -> Self {
+        let balance = balance::current();
+
+        // Taxes and market income
+        let taxes = if game.taxes_level != 0 && game.man_quantity > 0 {
+            let spread = game.man_quantity / 30 + 1;
+            let base = game.man_quantity * game.taxes_level as i32 * 10 / 30;
+            Band::roll(-spread, spread).map(|roll| (base + roll).max(0))
+        } else {
+            Band::exact(0)
+        };
+        let market_income = if game.market_quantity > 0 {
+            let spread = game.market_quantity as i32 + 1;
+            let income = game.with_condition(Building::Market, game.market_quantity as i32 * 200);
+            Band::roll(-spread, spread).map(|roll| income + roll)
+        } else {
+            Band::exact(0)
+        };
+        let income = taxes.add(market_income).map(|income| game.gold + income);
+
+        // Harvest and food
+        let harvest = if game.farm_quantity > 0 {
+            let farms = game.farm_quantity as i32;
+            Band::roll(0, 63)
+                .map(|roll| game.with_condition(Building::Farm, farms * 116 + farms * roll * 12))
+        } else {
+            Band::exact(0)
+        };
+        let needed = round::food_needed(game.man_quantity, game.food_supply);
+        let starved = game.food_quantity < needed;
+        let (eaten, food_level) = if starved {
+            (
+                game.food_quantity,
+                game.food_quantity * 100 / game.man_quantity / 34,
+            )
+        } else {
+            (needed, game.food_supply as i32)
+        };
+        let stores = harvest.map(|harvest| game.food_quantity - eaten + harvest);
+
+        // The soldiers are paid and fed from what is there after the harvest
+        let owed = Upkeep::owed(game.soldier_quantity);
+        let building_upkeep = game.building_upkeep();
+        let gold = income
+            .map(|gold| gold - owed.wages.min(gold.max(0)))
+            .map(|gold| gold - Maintenance::of(building_upkeep, gold).paid);
+        let food = stores.map(|food| food - owed.rations.min(food.max(0)));
+        let morale = if game.soldier_quantity > 0 {
+            let morale = |gold: i32, food: i32| {
+                let upkeep = Upkeep {
+                    paid: owed.wages.min(gold.max(0)),
+                    fed: owed.rations.min(food.max(0)),
+                    ..owed
+                };
+                game.morale
+                    .saturating_add(upkeep.morale_change())
+                    .clamp(0, 100) as i32
+            };
+            Band {
+                min: morale(income.min, stores.min),
+                expected: morale(income.expected, stores.expected),
+                max: morale(income.max, stores.max),
+            }
+        } else {
+            Band::exact(game.morale as i32)
+        };
+        let food_effect = balance.food_effects.get(food_level as usize);
+        let tax_effect = balance.tax_effects.get(game.taxes_level as usize);
+
+        // Popularity: starvation, food, taxes, then a drift of -4 to 4
+        let mut popularity = game.popularity_percent;
+        if starved {
+            popularity = add_popularity(popularity, -4);
+        }
+        popularity = add_popularity(
+            popularity,
+            food_effect.map_or(0, |effect| effect.popularity),
+        );
+        popularity = add_popularity(popularity, tax_effect.map_or(0, |effect| effect.popularity));
+        let popularity =
+            Band::roll(-4, 4).map(|drift| add_popularity(popularity, drift as i8) as i32);
+        let popularity_change = popularity.map(|value| value - game.popularity_percent as i32);
+
+        // Population: food, taxes times a roll of 4-7, then a drift of -4 to 4
+        let food_population = food_effect.map_or(0, |effect| {
+            game.man_quantity * effect.population_percent / 100
+        });
+        let citizens = add_citizens(game.man_quantity, food_population);
+        let multiplier = tax_effect.map_or(1, |effect| effect.population_multiplier);
+        let citizens = Band::roll(4, 7).map(|roll| add_citizens(citizens, roll * multiplier));
+        let citizens = Band {
+            min: add_citizens(citizens.min, -4),
+            expected: citizens.expected,
+            max: add_citizens(citizens.max, 4),
+        };
+        let population_change = citizens.map(|value| value - game.man_quantity);
+
+        // Next year, at the same supply level
+        let next_needed = citizens.map(|citizens| round::food_needed(citizens, game.food_supply));
+        let starvation = if starved {
+            StarvationRisk::Certain
+        } else if food.expected < next_needed.expected {
+            StarvationRisk::Likely
+        } else if food.min < next_needed.max {
+            StarvationRisk::Possible
+        } else {
+            StarvationRisk::None
+        };
+
+        Self {
+            taxes,
+            market_income,
+            wages: owed.wages,
+            rations: owed.rations,
+            building_upkeep,
+            gold,
+            food_needed: needed,
+            food_stock: game.food_quantity,
+            harvest,
+            food,
+            starvation,
+            popularity_change,
+            popularity,
+            morale,
+            population_change,
+            citizens,
+        }
+    }
+}
+
+/// Like `GameState::change_popularity`
+fn add_popularity(popularity: i8, amount: i8) -> i8 {
+    popularity.saturating_add(amount).clamp(0, 100)
+}
+
+/// Like `GameState::change_population`
+fn add_citizens(citizens: i32, amount: i32) -> i32 {
+    (citizens + amount).max(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::report::Ledger;
+    use crate::game::round;
+    use crate::game::state::test_game;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn within(band: Band, value: i32) -> bool {
+        band.min <= value
+            && value <= band.max
+            && band.min <= band.expected
+            && band.expected <= band.max
+    }
+
+    #[test]
+    fn test_forecast_covers_the_round() {
+        for (taxes, food_supply) in [(0, 0), (2, 3), (5, 5), (3, 1)] {
+            let mut game = test_game(11);
+            game.market_quantity = 2;
+            game.building_condition.farms = 70;
+            game.soldier_quantity = 40;
+            game.taxes_level = taxes;
+            game.food_supply = food_supply;
+            let forecast = Forecast::of(&game);
+
+            for seed in 0..50 {
+                let mut played = game.clone();
+                let mut ledger = Ledger::new(&played);
+                round::apply_round_effects(
+                    &mut played,
+                    &mut StdRng::seed_from_u64(seed),
+                    &mut ledger,
+                );
+                assert!(within(forecast.gold, played.gold));
+                assert!(within(forecast.food, played.food_quantity));
+                assert!(within(forecast.citizens, played.man_quantity));
+                assert!(within(
+                    forecast.popularity,
+                    played.popularity_percent as i32
+                ));
+                assert!(within(forecast.morale, played.morale as i32));
+            }
+        }
+    }
+
+    #[test]
+    fn test_starvation_risk() {
+        let mut game = test_game(11);
+        game.food_supply = 5;
+        game.food_quantity = round::food_needed(game.man_quantity, 5) - 1;
+        let forecast = Forecast::of(&game);
+        assert_eq!(forecast.starvation, StarvationRisk::Certain);
+        assert!(forecast.popularity_change.max < 4);
+
+        game.food_supply = 3;
+        game.food_quantity = 100_000;
+        let forecast = Forecast::of(&game);
+        assert_eq!(forecast.starvation, StarvationRisk::None);
+        assert_eq!(
+            forecast.food_needed,
+            round::food_needed(game.man_quantity, 3)
+        );
+        assert!(forecast.food.min >= 100_000 - forecast.food_needed);
+    }
+}
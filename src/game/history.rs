@@ -0,0 +1,120 @@
+//! Round history
+//! A compact snapshot of the key values at the start of the game and at the end of every
+//! round, for reports and charts about the whole reign.
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::state::GameState;
+
+/// The country at the end of one round. Round 0 holds the starting values.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RoundRecord {
+    pub round: u16,
+    pub year: u16,
+    pub gold: i32,
+    pub food: i32,
+    pub citizens: i32,
+    pub soldiers: i16,
+    pub popularity: i8,
+    pub morale: i8,
+    pub grade: u8,
+    pub food_price: i32,
+    pub iron_price: i32,
+    pub weapon_price: i32,
+    pub armor_price: i32,
+    pub castle_level: u8,
+    pub farms: i16,
+    pub mines: i16,
+    pub smithies: i16,
+    pub markets: i16,
+    /// Title of the round's event, if one happened
+    pub event: Option<String>,
+    pub promoted: bool,
+}
+
+impl RoundRecord {
+    pub fn of(game: &GameState, round: u16, year: u16) -> Self {
+        Self {
+            round,
+            year,
+            gold: game.gold,
+            food: game.food_quantity,
+            citizens: game.man_quantity,
+            soldiers: game.soldier_quantity,
+            popularity: game.popularity_percent,
+            morale: game.morale,
+            grade: game.grade,
+            food_price: game.price_for_food,
+            iron_price: game.price_for_iron,
+            weapon_price: game.price_for_weapon,
+            armor_price: game.price_for_armor,
+            castle_level: game.castle_level,
+            farms: game.farm_quantity,
+            mines: game.mine_quantity,
+            smithies: game.smithy_quantity,
+            markets: game.market_quantity,
+            event: None,
+            promoted: false,
+        }
+    }
+
+    pub fn is_start(&self) -> bool {
+        self.round == 0
+    }
+}
+
+impl GameState {
+    /// Starts the history of a new game with its starting values
+    pub(crate) fn begin_history(&mut self) {
+        self.history = vec![RoundRecord::of(self, 0, self.year)];
+    }
+
+    /// Adds the values at the end of the round played in `year`
+    pub fn record_round(&mut self, year: u16, event: Option<String>, promoted: bool) {
+        let record = RoundRecord {
+            event,
+            promoted,
+            ..RoundRecord::of(self, self.round, year)
+        };
+        self.history.push(record);
+    }
+
+    /// Updates the last round's values after a late change, such as a decision
+    pub fn refresh_last_round(&mut self) {
+        if let Some(last) = self.history.last() {
+            if last.is_start() {
+                return;
+            }
+            let record = RoundRecord {
+                event: last.event.clone(),
+                promoted: last.promoted,
+                ..RoundRecord::of(self, last.round, last.year)
+            };
+            *self.history.last_mut().unwrap() = record;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::round;
+    use crate::game::state::{Difficulty, GameState, Gender};
+
+    #[test]
+    fn test_history_records_every_round() {
+        let mut game = GameState::with_seed("Ada".to_string(), Gender::Female, Difficulty::Hard, 3);
+        assert_eq!(game.history.len(), 1);
+        assert!(game.history[0].is_start());
+        assert_eq!(game.history[0].gold, game.gold);
+
+        for _ in 0..3 {
+            round::play_seeded_round(&mut game);
+        }
+        assert_eq!(game.history.len(), 4);
+        let last = game.history.last().unwrap();
+        assert_eq!(last.round, 3);
+        assert_eq!(last.year + 1, game.year);
+        assert_eq!(last.food, game.food_quantity);
+    }
+}
@@ -1,5 +1,18 @@
+pub mod advisor;
+pub mod army;
+pub mod balance;
+pub mod battle;
+pub mod buildings;
+pub mod chronicle;
+pub mod error;
+pub mod event_data;
 pub mod events;
+pub mod forecast;
+pub mod history;
+pub mod report;
+pub mod rng;
+pub mod round;
+pub mod simulation;
 pub mod state;
 
-pub use events::EventGenerator;
-pub use state::{Difficulty, GameState, Gender};
+pub use state::{CustomRules, Difficulty, GameState, Gender};
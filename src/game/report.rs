@@ -0,0 +1,214 @@
+//! Round report
+//! A typed ledger of every step of a round, with the resources before and after each step.
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::battle::BattleReport;
+use crate::game::buildings::Maintenance;
+use crate::game::state::GameState;
+
+/// The resources and prices tracked by the ledger
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResourceSnapshot {
+    pub gold: i32,
+    pub food: i32,
+    pub population: i32,
+    pub popularity: i8,
+    pub soldiers: i16,
+    pub morale: i8,
+    pub iron: i16,
+    pub weapons: i16,
+    pub armor: i16,
+    pub price_for_food: i32,
+    pub price_for_iron: i32,
+    pub price_for_weapon: i32,
+    pub price_for_armor: i32,
+}
+
+impl ResourceSnapshot {
+    pub fn of(game: &GameState) -> Self {
+        Self {
+            gold: game.gold,
+            food: game.food_quantity,
+            population: game.man_quantity,
+            popularity: game.popularity_percent,
+            soldiers: game.soldier_quantity,
+            morale: game.morale,
+            iron: game.iron_quantity,
+            weapons: game.weapon_quantity,
+            armor: game.armor_quantity,
+            price_for_food: game.price_for_food,
+            price_for_iron: game.price_for_iron,
+            price_for_weapon: game.price_for_weapon,
+            price_for_armor: game.price_for_armor,
+        }
+    }
+
+    /// Every tracked value as (label, value), in display order
+    fn values(&self) -> [(&'static str, i32); 13] {
+        [
+            ("Gold", self.gold),
+            ("Food", self.food),
+            ("Citizens", self.population),
+            ("Popularity", self.popularity as i32),
+            ("Soldiers", self.soldiers as i32),
+            ("Morale", self.morale as i32),
+            ("Iron", self.iron as i32),
+            ("Weapons", self.weapons as i32),
+            ("Armor", self.armor as i32),
+            ("Food price", self.price_for_food),
+            ("Iron price", self.price_for_iron),
+            ("Weapon price", self.price_for_weapon),
+            ("Armor price", self.price_for_armor),
+        ]
+    }
+
+    /// The values that differ between `self` and `after`
+    pub fn changes_to(&self, after: &ResourceSnapshot) -> Vec<ResourceChange> {
+        self.values()
+            .iter()
+            .zip(after.values().iter())
+            .filter(|(before, after)| before.1 != after.1)
+            .map(|(before, after)| ResourceChange {
+                label: before.0,
+                before: before.1,
+                after: after.1,
+            })
+            .collect()
+    }
+}
+
+/// One value that changed during a step
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceChange {
+    pub label: &'static str,
+    pub before: i32,
+    pub after: i32,
+}
+
+impl ResourceChange {
+    pub fn delta(&self) -> i32 {
+        self.after - self.before
+    }
+}
+
+/// One line of the ledger
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoundStep {
+    pub label: String,
+    pub detail: String,
+    pub before: ResourceSnapshot,
+    pub after: ResourceSnapshot,
+}
+
+impl RoundStep {
+    pub fn changes(&self) -> Vec<ResourceChange> {
+        self.before.changes_to(&self.after)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoundEvent {
+    pub title: String,
+    pub description: String,
+    /// Outcome of the player's choice, once a decision event is answered
+    #[serde(default)]
+    pub decision: Option<String>,
+}
+
+/// Summary of what happened in one round
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RoundReport {
+    /// The year the round covered
+    pub year: u16,
+    pub taxes: i32,
+    pub market_income: i32,
+    pub harvest: i32,
+    pub harvest_percent: i32,
+    pub popularity_change: i8,
+    pub event: Option<RoundEvent>,
+    pub promoted: bool,
+    pub game_ended: bool,
+    /// The food stores ran out before everyone was fed
+    pub starved: bool,
+    /// The buildings' upkeep and the wear from what was left unpaid
+    pub maintenance: Maintenance,
+    /// The battle fought this round, by an event or a decision
+    pub battle: Option<BattleReport>,
+    pub start: ResourceSnapshot,
+    pub end: ResourceSnapshot,
+    /// Every step that changed something, in the order it was applied
+    pub steps: Vec<RoundStep>,
+}
+
+impl RoundReport {
+    /// Net change of every value over the whole round
+    pub fn total_changes(&self) -> Vec<ResourceChange> {
+        self.start.changes_to(&self.end)
+    }
+}
+
+/// Records a step each time the simulation changes the game
+#[derive(Debug)]
+pub struct Ledger {
+    last: ResourceSnapshot,
+    steps: Vec<RoundStep>,
+}
+
+impl Ledger {
+    pub fn new(game: &GameState) -> Self {
+        Self {
+            last: ResourceSnapshot::of(game),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Closes a step: everything that changed since the previous step is attributed to it.
+    /// Steps that changed nothing are left out.
+    pub fn record(&mut self, game: &GameState, label: &str, detail: String) {
+        let after = ResourceSnapshot::of(game);
+        if after != self.last {
+            self.steps.push(RoundStep {
+                label: label.to_string(),
+                detail,
+                before: self.last,
+                after,
+            });
+        }
+        self.last = after;
+    }
+
+    pub fn into_steps(self) -> Vec<RoundStep> {
+        self.steps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ledger_records_only_changes() {
+        let mut game = GameState::default();
+        let mut ledger = Ledger::new(&game);
+
+        game.gold += 100;
+        ledger.record(&game, "Taxes", String::new());
+        ledger.record(&game, "Nothing", String::new());
+        game.food_quantity -= 50;
+        game.change_popularity(-2);
+        ledger.record(&game, "Food", String::new());
+
+        let steps = ledger.into_steps();
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].changes()[0].delta(), 100);
+
+        let food_changes = steps[1].changes();
+        assert_eq!(food_changes.len(), 2);
+        assert_eq!(food_changes[0].label, "Food");
+        assert_eq!(food_changes[0].delta(), -50);
+        assert_eq!(food_changes[1].delta(), -2);
+    }
+}
@@ -0,0 +1,51 @@
+//! Seeded randomness
+//! Every game owns a generator derived from its seed, so two games with the same seed
+//! get the same harvests, prices and events.
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// The generator stored in `GameState`; serializable so saves continue the same sequence
+pub type GameRng = ChaCha8Rng;
+
+pub fn rng_from_seed(seed: u64) -> GameRng {
+    GameRng::seed_from_u64(seed)
+}
+
+/// A fresh random seed for a new game
+pub fn random_seed() -> u64 {
+    rand::thread_rng().gen()
+}
+
+/// Turns what a player typed into a seed: numbers are used as-is, any other text is
+/// hashed (FNV-1a, stable across builds) so words can be shared as seeds too.
+/// Returns `None` for blank input.
+pub fn parse_seed(text: &str) -> Option<u64> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    if let Ok(seed) = text.parse::<u64>() {
+        return Some(seed);
+    }
+
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in text.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    Some(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_seed() {
+        assert_eq!(parse_seed(" 12345 "), Some(12345));
+        assert_eq!(parse_seed(""), None);
+        assert_eq!(parse_seed("castle"), parse_seed("castle"));
+        assert_ne!(parse_seed("castle"), parse_seed("Castle"));
+    }
+}
@@ -0,0 +1,661 @@
+//! Round simulation
+//! Everything that happens when the player finishes a round, independent of the web layer.
+
+use rand::Rng;
+
+use crate::game::balance;
+use crate::game::battle;
+use crate::game::buildings::{Building, Maintenance};
+use crate::game::chronicle::ChronicleKind;
+use crate::game::error::GameError;
+use crate::game::events::EventGenerator;
+use crate::game::report::{Ledger, ResourceSnapshot, RoundEvent, RoundReport};
+use crate::game::state::{GameState, ARMOR_PER_SMITHY, IRON_PER_ARMOR};
+
+/// Plays one round: applies the economy, rolls a random event and checks for promotion
+/// and game over. All randomness comes from `rng`.
+pub fn play_round<R: Rng + ?Sized>(game: &mut GameState, rng: &mut R) -> RoundReport {
+    let year = game.year;
+
+    // Store previous popularity for comparison
+    game.previous_popularity_percent = game.popularity_percent;
+
+    // A decision left open is settled with its first choice
+    if game.pending_event.is_some() {
+        let _ = resolve_event(game, 0);
+    }
+
+    let start = ResourceSnapshot::of(game);
+    let mut ledger = Ledger::new(game);
+    let (starved, maintenance) = apply_round_effects(game, rng, &mut ledger);
+
+    // Generate random event
+    let mut event = None;
+    let mut battle = None;
+    game.last_event_title = None;
+    game.last_event_description = None;
+    if rng.gen::<f32>() < game.event_chance() {
+        if let Some(generated) = EventGenerator::generate_random_event(game, rng) {
+            // Store event for display in report
+            game.last_event_title = Some(generated.title.clone());
+            game.last_event_description = Some(generated.description.clone());
+            event = Some(RoundEvent {
+                title: generated.title.clone(),
+                description: generated.description.clone(),
+                decision: None,
+            });
+            if generated.is_decision() {
+                // Applied once the player chooses, see `resolve_event`
+                game.pending_event = Some(generated);
+            } else {
+                generated.apply_to_state(game);
+                if let Some(kind) = generated.effects.battle {
+                    // The battle goes into the chronicle on its own
+                    ledger.record(game, "Event", generated.title.clone());
+                    let fought = battle::fight(game, kind, year, rng);
+                    ledger.record(game, "Battle", fought.title().to_string());
+                    let description = format!("{} {}", generated.description, fought.summary());
+                    game.last_event_description = Some(description.clone());
+                    if let Some(ref mut event) = event {
+                        event.description = description;
+                    }
+                    battle = Some(fought);
+                } else {
+                    game.chronicle_entry(
+                        year,
+                        ChronicleKind::Event,
+                        generated.title.clone(),
+                        generated.description,
+                        generated.effects.describe(),
+                    );
+                    ledger.record(game, "Event", generated.title);
+                }
+            }
+        }
+    }
+
+    // Check for grade advancement and game over
+    let previous_title = game.get_grade_title();
+    let promoted = game.finish_round();
+    if promoted {
+        let title = format!("Promoted to {}", game.get_grade_title());
+        let text = format!(
+            "{} {} is promoted to {}.",
+            previous_title,
+            game.user_name,
+            game.get_grade_title()
+        );
+        game.chronicle_entry(year, ChronicleKind::Promotion, title, text, Vec::new());
+    }
+    game.record_milestones(year);
+    let event_title = event.as_ref().map(|event: &RoundEvent| event.title.clone());
+    game.record_round(year, event_title, promoted);
+    if game.is_game_ended {
+        let (title, text) = if game.is_won {
+            (
+                "The reign is crowned",
+                "The empire is yours. Your name will be remembered.",
+            )
+        } else {
+            (
+                "The reign ends",
+                "Time has run out before you could become Emperor.",
+            )
+        };
+        game.chronicle_entry(year, ChronicleKind::Reign, title, text, Vec::new());
+    }
+
+    RoundReport {
+        year,
+        taxes: game.taxes_value,
+        market_income: game.market_place_value,
+        harvest: game.harvest_value,
+        harvest_percent: game.harvest_percent,
+        popularity_change: game.popularity_percent - game.previous_popularity_percent,
+        event,
+        promoted,
+        game_ended: game.is_game_ended,
+        starved,
+        maintenance,
+        battle,
+        start,
+        end: ResourceSnapshot::of(game),
+        steps: ledger.into_steps(),
+    }
+}
+
+/// Plays one round using the game's own seeded generator, so the outcome
+/// depends only on the seed and the player's decisions
+pub fn play_seeded_round(game: &mut GameState) -> RoundReport {
+    let mut rng = game.rng.clone();
+    let report = play_round(game, &mut rng);
+    game.rng = rng;
+    game.last_report = Some(report.clone());
+    report
+}
+
+/// Answers the pending decision event with choice `index` and adds the outcome to the
+/// last round's report
+pub fn resolve_event(game: &mut GameState, index: usize) -> Result<(), GameError> {
+    let event = game
+        .pending_event
+        .as_ref()
+        .ok_or(GameError::NoDecisionPending)?;
+    let choice = event
+        .choices
+        .get(index)
+        .ok_or(GameError::InvalidChoice)?
+        .clone();
+    let title = event.title.clone();
+    game.pending_event = None;
+
+    let year = game
+        .last_report
+        .as_ref()
+        .map_or(game.year, |report| report.year);
+    let mut ledger = Ledger::new(game);
+    choice.effects.apply_to_state(game);
+    ledger.record(game, "Decision", choice.label.clone());
+    game.chronicle_entry(
+        year,
+        ChronicleKind::Decision,
+        title,
+        choice.outcome.clone(),
+        choice.effects.describe(),
+    );
+
+    let fought = choice.effects.battle.map(|kind| {
+        let mut rng = game.rng.clone();
+        let fought = battle::fight(game, kind, year, &mut rng);
+        game.rng = rng;
+        ledger.record(game, "Battle", fought.title().to_string());
+        fought
+    });
+    let outcome = match fought {
+        Some(ref fought) => format!("{} {}", choice.outcome, fought.summary()),
+        None => choice.outcome,
+    };
+
+    game.refresh_last_round();
+
+    let end = ResourceSnapshot::of(game);
+    if let Some(ref mut report) = game.last_report {
+        report.steps.extend(ledger.into_steps());
+        report.end = end;
+        if let Some(ref mut event) = report.event {
+            event.decision = Some(outcome);
+        }
+        if fought.is_some() {
+            report.battle = fought;
+        }
+    }
+    Ok(())
+}
+
+/// The economic simulation: income, production, harvest, food and population changes, prices.
+/// Every step is closed on `ledger` so the report can show what it changed.
+/// Returns whether the food stores ran out, and what the buildings' upkeep came to.
+pub(crate) fn apply_round_effects<R: Rng + ?Sized>(
+    game: &mut GameState,
+    rng: &mut R,
+    ledger: &mut Ledger,
+) -> (bool, Maintenance) {
+    // 1. Collect taxes (with randomness)
+    if game.taxes_level != 0 && game.man_quantity > 0 {
+        let random_value =
+            rng.gen_range(-(game.man_quantity / 30 + 1)..=(game.man_quantity / 30 + 1));
+        game.taxes_value = game.man_quantity * game.taxes_level as i32 * 10 / 30 + random_value;
+        if game.taxes_value < 0 {
+            game.taxes_value = 0;
+        }
+    } else {
+        game.taxes_value = 0;
+    }
+    game.gold += game.taxes_value;
+    ledger.record(
+        game,
+        "Taxes collected",
+        format!("Tax level {}", game.taxes_level),
+    );
+
+    // 2. Collect market income (with randomness)
+    if game.market_quantity > 0 {
+        let random_value =
+            rng.gen_range(-(game.market_quantity as i32 + 1)..=(game.market_quantity as i32 + 1));
+        game.market_place_value =
+            game.with_condition(Building::Market, game.market_quantity as i32 * 200) + random_value;
+    } else {
+        game.market_place_value = 0;
+    }
+    game.gold += game.market_place_value;
+    ledger.record(
+        game,
+        "Market income",
+        format!("{} markets", game.market_quantity),
+    );
+
+    // 3. Calculate weapon production (happens BEFORE mine production)
+    if game.smithy_quantity > 0 {
+        let mut var3 = game.with_condition(Building::Smithy, game.smithy_quantity as i32 * 8);
+        if var3 > game.iron_quantity as i32 {
+            var3 = game.iron_quantity as i32;
+        }
+        if game.weapon_quantity as i32 + var3 > game.trade_limit as i32 {
+            var3 = game.trade_limit as i32 - game.weapon_quantity as i32;
+        }
+        game.weapon_quantity += var3 as i16;
+        game.iron_quantity -= var3 as i16;
+    }
+    ledger.record(
+        game,
+        "Weapon production",
+        format!("{} smithies", game.smithy_quantity),
+    );
+
+    // 3b. Calculate armor production from the iron the weapons left over
+    if game.smithy_quantity > 0 {
+        let armor = (game.with_condition(
+            Building::Smithy,
+            (game.smithy_quantity * ARMOR_PER_SMITHY) as i32,
+        ) as i16)
+            .min(game.iron_quantity / IRON_PER_ARMOR)
+            .min(game.trade_limit - game.armor_quantity)
+            .max(0);
+        game.armor_quantity += armor;
+        game.iron_quantity -= armor * IRON_PER_ARMOR;
+    }
+    ledger.record(
+        game,
+        "Armor production",
+        format!("{} smithies", game.smithy_quantity),
+    );
+
+    // 4. Calculate mine production (happens AFTER weapon production)
+    if game.mine_quantity > 0 {
+        let iron = game.with_condition(Building::Mine, game.mine_quantity as i32 * 10);
+        if game.iron_quantity as i32 + iron > game.trade_limit as i32 {
+            game.iron_quantity = game.trade_limit;
+        } else {
+            game.iron_quantity += iron as i16;
+        }
+    }
+    ledger.record(
+        game,
+        "Mine production",
+        format!("{} mines", game.mine_quantity),
+    );
+
+    // 5. Calculate harvest (with randomness)
+    if game.farm_quantity > 0 {
+        let random_value = rng.gen_range(0..64);
+        game.harvest_value = game.with_condition(
+            Building::Farm,
+            game.farm_quantity as i32 * 116 + game.farm_quantity as i32 * random_value * 12,
+        );
+        game.harvest_percent = game.harvest_value / (game.farm_quantity as i32 * 5);
+    } else {
+        game.harvest_value = 0;
+        game.harvest_percent = 0;
+    }
+
+    // 6. Calculate food consumption
+    let food_needed = food_needed(game.man_quantity, game.food_supply);
+
+    let actual_food_level: i32;
+    let starved = game.food_quantity < food_needed;
+    if !starved {
+        game.food_quantity -= food_needed;
+        actual_food_level = game.food_supply as i32;
+        ledger.record(
+            game,
+            "Food consumption",
+            format!("{} food needed", food_needed),
+        );
+    } else {
+        actual_food_level = game.food_quantity * 100 / game.man_quantity / 34;
+        game.food_quantity = 0;
+        ledger.record(
+            game,
+            "Food consumption",
+            format!("{} food needed, stores ran out", food_needed),
+        );
+        game.change_popularity(-4);
+        ledger.record(
+            game,
+            "Starvation",
+            "Not enough food for everyone".to_string(),
+        );
+    }
+
+    // 7. Food supply effects on popularity and population
+    let food_effects = &balance::current().food_effects;
+    let (food_popularity_change, food_population_change) =
+        match food_effects.get(actual_food_level as usize) {
+            Some(effect) => (
+                effect.popularity,
+                game.man_quantity * effect.population_percent / 100,
+            ),
+            None => (0, 0),
+        };
+
+    game.change_popularity(food_popularity_change);
+    game.change_population(food_population_change);
+    ledger.record(
+        game,
+        "Food supply effects",
+        format!("Citizens ate at level {}", actual_food_level),
+    );
+
+    // 8. Add harvest to food
+    game.food_quantity += game.harvest_value;
+    ledger.record(
+        game,
+        "Harvest",
+        format!("{} farms at {}%", game.farm_quantity, game.harvest_percent),
+    );
+
+    // 8b. Pay and feed the soldiers; their morale follows
+    let upkeep = game.pay_army();
+    ledger.record(
+        game,
+        "Army upkeep",
+        format!(
+            "{} soldiers paid {} of {} gold, fed {} of {} food",
+            game.soldier_quantity, upkeep.paid, upkeep.wages, upkeep.fed, upkeep.rations
+        ),
+    );
+    if game.soldier_quantity > 0 {
+        game.change_morale(upkeep.morale_change());
+    }
+    ledger.record(game, "Army morale", game.morale_label().to_string());
+
+    // 8c. Soldiers with low morale desert
+    let deserters = game.desert(rng);
+    ledger.record(
+        game,
+        "Desertion",
+        format!("{} soldiers left the army", deserters),
+    );
+
+    // 8d. Keep up the buildings; what cannot be paid wears them down
+    let maintenance = game.maintain_buildings();
+    let detail = if maintenance.decay > 0 {
+        format!(
+            "Paid {} of {} gold, buildings lose {}% condition",
+            maintenance.paid, maintenance.upkeep, maintenance.decay
+        )
+    } else {
+        format!("Paid {} gold", maintenance.paid)
+    };
+    ledger.record(game, "Building upkeep", detail);
+
+    // 9. Tax level effects on popularity and population (with randomness)
+    let random_base = rng.gen_range(0..4) + 4; // Generates 4-7
+    let (tax_popularity_change, tax_population_multiplier) = match balance::current()
+        .tax_effects
+        .get(game.taxes_level as usize)
+    {
+        Some(effect) => (effect.popularity, effect.population_multiplier),
+        None => (0, 1),
+    };
+
+    game.change_popularity(tax_popularity_change);
+    game.change_population(random_base * tax_population_multiplier);
+    ledger.record(
+        game,
+        "Tax effects",
+        format!("Tax level {}", game.taxes_level),
+    );
+
+    // 10. Random population and popularity fluctuations
+    let random_population = rng.gen_range(-4..=4);
+    game.change_population(random_population);
+
+    let random_popularity = rng.gen_range(-4..=4);
+    game.change_popularity(random_popularity);
+    ledger.record(game, "Random drift", String::new());
+
+    // 11. Adjust food prices based on harvest
+    if game.farm_quantity > 0 {
+        let mut price_for_food =
+            game.price_for_food_rate_constant as i32 * 100 / game.harvest_percent;
+        let random_value = rng.gen_range(0..(price_for_food / 5));
+
+        price_for_food = price_for_food.clamp(25, 100);
+
+        // Worn farms can make the roll larger than the clamped price
+        price_for_food = (price_for_food - random_value).max(1);
+        game.price_for_food = price_for_food;
+    } else {
+        let random_value = rng.gen_range(
+            -(game.price_for_food_rate_constant as i32 * 50 / 100)
+                ..=(game.price_for_food_rate_constant as i32 * 50 / 100),
+        );
+        game.price_for_food = random_value + game.price_for_food_rate_constant as i32;
+    }
+    ledger.record(game, "Food market", String::new());
+
+    // 12. Adjust iron prices (with randomness)
+    let var6 = game.price_for_iron_rate_constant as i32 * 20 / 100;
+    let random_value = rng.gen_range(-var6..=var6);
+    game.price_for_iron = game.price_for_iron_rate_constant as i32 + random_value;
+
+    // 13. Adjust weapon prices (with randomness)
+    let var6 = game.price_for_weapon_rate_constant as i32 * 20 / 100;
+    let random_value = rng.gen_range(-var6..=var6);
+    game.price_for_weapon = game.price_for_weapon_rate_constant as i32 + random_value;
+
+    // 14. Adjust armor prices (with randomness)
+    let var6 = game.price_for_armor_rate_constant as i32 * 20 / 100;
+    let random_value = rng.gen_range(-var6..=var6);
+    game.price_for_armor = game.price_for_armor_rate_constant as i32 + random_value;
+    ledger.record(game, "Arms market", String::new());
+
+    (starved, maintenance)
+}
+
+/// Food eaten in a round by `citizens` at food supply level `food_supply`
+pub fn food_needed(citizens: i32, food_supply: u8) -> i32 {
+    (citizens * (food_supply as i32 * 34 - 2) / 100).max(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::battle::BattleKind;
+    use crate::game::buildings::MIN_CONDITION;
+    use crate::game::events::{EventChoice, EventEffects, GameEvent};
+    use crate::game::{CustomRules, Difficulty, Gender};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn new_game() -> GameState {
+        GameState::new("Tester".to_string(), Gender::Female, Difficulty::Medium)
+    }
+
+    #[test]
+    fn test_round_advances_year() {
+        let mut game = new_game();
+        let report = play_round(&mut game, &mut StdRng::seed_from_u64(1));
+        assert_eq!(report.year, 1445);
+        assert_eq!(game.year, 1446);
+        assert_eq!(game.round, 1);
+        assert_eq!(report.taxes, game.taxes_value);
+    }
+
+    #[test]
+    fn test_smithies_make_armor_from_leftover_iron() {
+        let mut game = new_game();
+        game.smithy_quantity = 2;
+        game.iron_quantity = 21;
+        play_round(&mut game, &mut StdRng::seed_from_u64(1));
+        // 16 iron become weapons, the other 5 make 2 armor
+        assert_eq!(game.armor_quantity, 2);
+        assert_eq!(game.iron_quantity, 1);
+    }
+
+    #[test]
+    fn test_worn_farms_keep_food_price_positive() {
+        let mut game = new_game();
+        game.farm_quantity = 3;
+        game.building_condition.farms = MIN_CONDITION;
+        for seed in 0..50 {
+            let mut game = game.clone();
+            play_round(&mut game, &mut StdRng::seed_from_u64(seed));
+            assert!(
+                game.price_for_food > 0,
+                "seed {}: food price {}",
+                seed,
+                game.price_for_food
+            );
+        }
+    }
+
+    #[test]
+    fn test_same_rng_same_outcome() {
+        let mut first = new_game();
+        let mut second = new_game();
+        let mut rng_a = StdRng::seed_from_u64(42);
+        let mut rng_b = StdRng::seed_from_u64(42);
+        for _ in 0..20 {
+            play_round(&mut first, &mut rng_a);
+            play_round(&mut second, &mut rng_b);
+        }
+        assert_eq!(first.gold, second.gold);
+        assert_eq!(first.food_quantity, second.food_quantity);
+        assert_eq!(first.man_quantity, second.man_quantity);
+        assert_eq!(first.price_for_food, second.price_for_food);
+    }
+
+    #[test]
+    fn test_same_seed_same_game() {
+        let mut first = GameState::with_seed("A".to_string(), Gender::Male, Difficulty::Easy, 99);
+        let mut second = GameState::with_seed("B".to_string(), Gender::Male, Difficulty::Easy, 99);
+        for _ in 0..20 {
+            let a = play_seeded_round(&mut first);
+            let b = play_seeded_round(&mut second);
+            assert_eq!(a.harvest, b.harvest);
+            assert_eq!(a.event.map(|e| e.title), b.event.map(|e| e.title));
+        }
+        assert_eq!(first.price_for_food, second.price_for_food);
+    }
+
+    #[test]
+    fn test_ledger_adds_up() {
+        let mut game = new_game();
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..10 {
+            let report = play_round(&mut game, &mut rng);
+            assert_eq!(report.end, ResourceSnapshot::of(&game));
+            let mut current = report.start;
+            for step in &report.steps {
+                assert_eq!(step.before, current);
+                current = step.after;
+            }
+            assert_eq!(current, report.end);
+        }
+    }
+
+    #[test]
+    fn test_game_ends_after_last_year() {
+        let mut game = new_game();
+        game.year = 1500;
+        let report = play_round(&mut game, &mut StdRng::seed_from_u64(7));
+        assert!(report.game_ended);
+        assert!(!game.is_won);
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        let rules = CustomRules {
+            gold: 50000,
+            start_year: 1600,
+            end_year: 1601,
+            event_percent: 0,
+            year_limit: false,
+            ..Default::default()
+        };
+        let mut game = GameState::with_rules("Tester".to_string(), Gender::Male, rules, 3);
+        assert_eq!(game.user_difficulty, Difficulty::Custom);
+        assert_eq!((game.gold, game.year), (50000, 1600));
+
+        // No events at 0%, and the reign goes on past the end year
+        for _ in 0..3 {
+            let report = play_seeded_round(&mut game);
+            assert!(report.event.is_none());
+            assert!(!report.game_ended);
+        }
+    }
+
+    #[test]
+    fn test_resolve_event() {
+        let mut game = new_game();
+        play_seeded_round(&mut game);
+        // The round itself may have rolled a decision
+        game.pending_event = None;
+        assert_eq!(
+            resolve_event(&mut game, 0),
+            Err(GameError::NoDecisionPending)
+        );
+
+        let choice = |gold_change| EventChoice {
+            label: "Choice".to_string(),
+            outcome: "Outcome".to_string(),
+            effects: EventEffects {
+                gold_change,
+                ..Default::default()
+            },
+        };
+        game.pending_event = Some(GameEvent {
+            title: "Tribute".to_string(),
+            description: String::new(),
+            effects: EventEffects::default(),
+            choices: vec![choice(-100), choice(0)],
+        });
+        assert_eq!(resolve_event(&mut game, 2), Err(GameError::InvalidChoice));
+
+        let gold = game.gold;
+        resolve_event(&mut game, 0).unwrap();
+        assert_eq!(game.gold, gold - 100);
+        assert!(game.pending_event.is_none());
+
+        let report = game.last_report.as_ref().unwrap();
+        assert_eq!(report.steps.last().unwrap().label, "Decision");
+        assert_eq!(report.end.gold, game.gold);
+    }
+
+    #[test]
+    fn test_decision_fights_battle() {
+        let mut game = new_game();
+        play_seeded_round(&mut game);
+        game.soldier_quantity = 100;
+        game.pending_event = Some(GameEvent {
+            title: "Tribute".to_string(),
+            description: String::new(),
+            effects: EventEffects::default(),
+            choices: vec![EventChoice {
+                label: "Fight".to_string(),
+                outcome: "You refuse.".to_string(),
+                effects: EventEffects {
+                    battle: Some(BattleKind::Defense),
+                    ..Default::default()
+                },
+            }],
+        });
+        resolve_event(&mut game, 0).unwrap();
+
+        let report = game.last_report.as_ref().unwrap();
+        let battle = report.battle.as_ref().unwrap();
+        assert_eq!(battle.year, report.year);
+        assert_eq!(report.steps.last().unwrap().label, "Battle");
+        assert_eq!(game.soldier_quantity, 100 - battle.soldiers_lost);
+        let kinds: Vec<ChronicleKind> = game
+            .chronicle
+            .iter()
+            .rev()
+            .take(2)
+            .map(|e| e.kind)
+            .collect();
+        assert_eq!(kinds, [ChronicleKind::Battle, ChronicleKind::Decision]);
+    }
+}
@@ -0,0 +1,209 @@
+//! Balance simulation
+//! Plays many seeded games with a strategy and summarizes how they went, so changes to the
+//! difficulty modifiers, grade thresholds and harvest formula can be judged by numbers.
+
+use serde::Serialize;
+
+use crate::game::advisor::{self, Strategy, StrategyKind};
+use crate::game::round;
+use crate::game::state::{Difficulty, GameState, Gender};
+
+/// Number of grades above the starting one
+pub const GRADE_COUNT: usize = 5;
+
+/// How one simulated game ended
+#[derive(Debug, Clone, Serialize)]
+pub struct GameOutcome {
+    pub seed: u64,
+    pub won: bool,
+    pub final_year: u16,
+    pub final_grade: u8,
+    pub score: i32,
+    /// The treasury was empty at the end of at least one round
+    pub went_bankrupt: bool,
+    /// The food stores ran out in at least one round
+    pub starved: bool,
+    /// Rounds it took to reach each grade, if it was reached
+    pub grade_rounds: [Option<u16>; GRADE_COUNT],
+}
+
+/// Plays one game from `seed` to the end
+pub fn simulate_game(strategy: &dyn Strategy, difficulty: Difficulty, seed: u64) -> GameOutcome {
+    let mut game = GameState::with_seed("Simulator".to_string(), Gender::Male, difficulty, seed);
+    let mut outcome = GameOutcome {
+        seed,
+        won: false,
+        final_year: game.year,
+        final_grade: 0,
+        score: 0,
+        went_bankrupt: false,
+        starved: false,
+        grade_rounds: [None; GRADE_COUNT],
+    };
+
+    while !game.is_game_ended {
+        advisor::play_turn(strategy, &mut game);
+        let report = round::play_seeded_round(&mut game);
+
+        outcome.starved |= report.starved;
+        outcome.went_bankrupt |= game.gold <= 0;
+        if report.promoted {
+            outcome.grade_rounds[game.grade as usize - 1] = Some(game.round);
+        }
+    }
+
+    outcome.won = game.is_won;
+    outcome.final_year = game.year;
+    outcome.final_grade = game.grade;
+    outcome.score = game.calculate_score();
+    outcome
+}
+
+/// Plays `games` games with consecutive seeds starting at `first_seed`
+pub fn simulate(
+    strategy: &dyn Strategy,
+    difficulty: Difficulty,
+    first_seed: u64,
+    games: u32,
+) -> Vec<GameOutcome> {
+    (0..games as u64)
+        .map(|offset| simulate_game(strategy, difficulty, first_seed.wrapping_add(offset)))
+        .collect()
+}
+
+/// Spread of final scores
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScoreDistribution {
+    pub min: i32,
+    pub p25: i32,
+    pub median: i32,
+    pub p75: i32,
+    pub max: i32,
+    pub mean: f64,
+}
+
+impl ScoreDistribution {
+    fn of(scores: &mut [i32]) -> Self {
+        if scores.is_empty() {
+            return Self::default();
+        }
+        scores.sort_unstable();
+        let at = |fraction: f64| scores[((scores.len() - 1) as f64 * fraction).round() as usize];
+        Self {
+            min: scores[0],
+            p25: at(0.25),
+            median: at(0.5),
+            p75: at(0.75),
+            max: scores[scores.len() - 1],
+            mean: scores.iter().map(|&s| s as f64).sum::<f64>() / scores.len() as f64,
+        }
+    }
+}
+
+/// How often and how fast one grade was reached
+#[derive(Debug, Clone, Serialize)]
+pub struct GradeTiming {
+    pub grade: u8,
+    pub reached_rate: f64,
+    /// Mean rounds to reach the grade, over the games that reached it
+    pub mean_rounds: Option<f64>,
+}
+
+/// Aggregate results for one strategy on one difficulty
+#[derive(Debug, Clone, Serialize)]
+pub struct Summary {
+    pub strategy: StrategyKind,
+    pub difficulty: Difficulty,
+    pub games: usize,
+    pub win_rate: f64,
+    pub mean_final_year: f64,
+    pub scores: ScoreDistribution,
+    pub bankruptcy_rate: f64,
+    pub starvation_rate: f64,
+    pub grades: Vec<GradeTiming>,
+}
+
+impl Summary {
+    pub fn of(strategy: StrategyKind, difficulty: Difficulty, outcomes: &[GameOutcome]) -> Self {
+        let games = outcomes.len();
+        let rate = |count: usize| {
+            if games == 0 {
+                0.0
+            } else {
+                count as f64 / games as f64
+            }
+        };
+
+        let mut scores: Vec<i32> = outcomes.iter().map(|o| o.score).collect();
+        let grades = (0..GRADE_COUNT)
+            .map(|index| {
+                let rounds: Vec<u16> = outcomes
+                    .iter()
+                    .filter_map(|o| o.grade_rounds[index])
+                    .collect();
+                GradeTiming {
+                    grade: index as u8 + 1,
+                    reached_rate: rate(rounds.len()),
+                    mean_rounds: (!rounds.is_empty()).then(|| {
+                        rounds.iter().map(|&r| r as f64).sum::<f64>() / rounds.len() as f64
+                    }),
+                }
+            })
+            .collect();
+
+        Self {
+            strategy,
+            difficulty,
+            games,
+            win_rate: rate(outcomes.iter().filter(|o| o.won).count()),
+            mean_final_year: if games == 0 {
+                0.0
+            } else {
+                outcomes.iter().map(|o| o.final_year as f64).sum::<f64>() / games as f64
+            },
+            scores: ScoreDistribution::of(&mut scores),
+            bankruptcy_rate: rate(outcomes.iter().filter(|o| o.went_bankrupt).count()),
+            starvation_rate: rate(outcomes.iter().filter(|o| o.starved).count()),
+            grades,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simulation_is_reproducible() {
+        let strategy = StrategyKind::Balanced.strategy();
+        let first = simulate(strategy, Difficulty::Easy, 10, 3);
+        let second = simulate(strategy, Difficulty::Easy, 10, 3);
+        for (a, b) in first.iter().zip(&second) {
+            assert_eq!(a.final_year, b.final_year);
+            assert_eq!(a.grade_rounds, b.grade_rounds);
+        }
+    }
+
+    #[test]
+    fn test_summary() {
+        let outcome = |score, won| GameOutcome {
+            seed: 0,
+            won,
+            final_year: 1480,
+            final_grade: 5,
+            score,
+            went_bankrupt: !won,
+            starved: false,
+            grade_rounds: [Some(5), Some(10), None, None, None],
+        };
+        let outcomes = [outcome(0, false), outcome(100, true), outcome(300, true)];
+        let summary = Summary::of(StrategyKind::Rush, Difficulty::Hard, &outcomes);
+
+        assert_eq!(summary.games, 3);
+        assert!((summary.win_rate - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(summary.scores.median, 100);
+        assert_eq!(summary.scores.max, 300);
+        assert_eq!(summary.grades[1].mean_rounds, Some(10.0));
+        assert_eq!(summary.grades[2].mean_rounds, None);
+    }
+}
@@ -1,11 +1,44 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::game::balance;
+use crate::game::battle::BattleReport;
+use crate::game::buildings::{Building, BuildingCondition};
+use crate::game::chronicle::{ChronicleEntry, ChronicleKind};
+use crate::game::error::GameError;
+use crate::game::events::{EventRecord, GameEvent};
+use crate::game::history::RoundRecord;
+use crate::game::report::RoundReport;
+use crate::game::rng::{random_seed, rng_from_seed, GameRng};
+
+/// Markets needed before iron can be bought or sold
+pub const IRON_TRADE_MARKETS: i16 = 5;
+
+/// Markets needed before weapons can be bought or sold
+pub const WEAPON_TRADE_MARKETS: i16 = 10;
+
+/// Markets needed before armor can be bought or sold
+pub const ARMOR_TRADE_MARKETS: i16 = 10;
+
+/// Armor each smithy makes per round from the iron left after forging weapons
+pub const ARMOR_PER_SMITHY: i16 = 4;
+
+/// Iron used for one armor
+pub const IRON_PER_ARMOR: i16 = 2;
+
+/// Citizens that must stay behind when recruiting soldiers
+pub const MIN_CITIZENS: i32 = 200;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct GameState {
     // Player info
     pub user_name: String,
     pub user_sex: Gender,
     pub user_difficulty: Difficulty,
+    /// The player's own starting conditions when `user_difficulty` is `Custom`
+    pub custom_rules: Option<CustomRules>,
 
     // Time
     pub year: u16,
@@ -18,6 +51,9 @@ pub struct GameState {
     pub soldier_quantity: i16,
     pub weapon_quantity: i16,
     pub iron_quantity: i16,
+    pub armor_quantity: i16,
+    /// Soldiers that wear armor, at most `soldier_quantity`
+    pub armored_soldier_quantity: i16,
 
     // Buildings
     pub castle_level: u8,
@@ -25,6 +61,8 @@ pub struct GameState {
     pub mine_quantity: i16,
     pub smithy_quantity: i16,
     pub market_quantity: i16,
+    /// How well the buildings are kept up; see `buildings`
+    pub building_condition: BuildingCondition,
 
     // Prices
     pub price_for_castle: i32,
@@ -33,12 +71,14 @@ pub struct GameState {
     pub price_for_smithy: i32,
     pub price_for_market: i32,
     pub price_for_food: i32,
+    pub price_for_iron: i32,
     pub price_for_armor: i32,
     pub price_for_weapon: i32,
     pub soldier_price: i32,
 
     // Price rate constants (for price fluctuation)
     pub price_for_food_rate_constant: i16,
+    pub price_for_iron_rate_constant: i16,
     pub price_for_armor_rate_constant: i16,
     pub price_for_weapon_rate_constant: i16,
 
@@ -49,9 +89,12 @@ pub struct GameState {
     // Stats
     pub popularity_percent: i8,
     pub previous_popularity_percent: i8,
+    /// How willing the soldiers are to serve, 0 to 100; see `army`
+    pub morale: i8,
     pub grade: u8,
     pub was_grade_up_before: bool,
     pub is_castle_upgrade_in_this_round: bool,
+    pub has_raided_this_round: bool,
 
     // Round results
     pub taxes_value: i32,
@@ -63,10 +106,37 @@ pub struct GameState {
     pub last_event_title: Option<String>,
     pub last_event_description: Option<String>,
 
+    // Ledger of the last finished round (for report display)
+    pub last_report: Option<RoundReport>,
+
+    // The last battle fought, for the battle report
+    pub last_battle: Option<BattleReport>,
+
+    // Decision event from the last round the player still has to answer
+    pub pending_event: Option<GameEvent>,
+
+    // How often each event happened, for event cooldowns and limits
+    pub event_history: BTreeMap<String, EventRecord>,
+
+    // Events, promotions and milestones of the whole reign. Games saved before the
+    // chronicle was kept load with none, not with another ruler's accession.
+    #[serde(default)]
+    pub chronicle: Vec<ChronicleEntry>,
+
+    // Values at the start and at the end of every round. Games saved before the history
+    // was kept load with none, not with the start of a new game.
+    #[serde(default)]
+    pub history: Vec<RoundRecord>,
+
     // Game state
     pub is_game_ended: bool,
     pub is_won: bool,
+    pub is_score_recorded: bool,
     pub trade_limit: i16,
+
+    // Randomness: all rolls come from `rng`, which starts from `seed`
+    pub seed: u64,
+    pub rng: GameRng,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -80,92 +150,237 @@ pub enum Difficulty {
     Easy,
     Medium,
     Hard,
+    Custom,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Medium,
+        Difficulty::Hard,
+        Difficulty::Custom,
+    ];
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Medium => "Medium",
+            Difficulty::Hard => "Hard",
+            Difficulty::Custom => "Custom",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Starting conditions chosen on the new-game form for a `Custom` game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CustomRules {
+    pub gold: i32,
+    pub food: i32,
+    pub citizens: i32,
+    pub farms: i16,
+    pub start_year: u16,
+    pub end_year: u16,
+    /// Chance of a random event at the end of a round, in percent
+    pub event_percent: u8,
+    pub trade_limit: i16,
+    /// Whether the game is lost once `end_year` has passed
+    pub year_limit: bool,
+}
+
+impl Default for CustomRules {
+    /// The Easy starting conditions
+    fn default() -> Self {
+        let balance = balance::current();
+        Self {
+            gold: balance.start.gold,
+            food: balance.start.food,
+            citizens: balance.start.citizens,
+            farms: balance.start.farms,
+            start_year: balance.start.year,
+            end_year: balance.start.end_year,
+            event_percent: (balance.events.chance * 100.0).round() as u8,
+            trade_limit: balance.start.trade_limit,
+            year_limit: true,
+        }
+    }
+}
+
+impl CustomRules {
+    pub fn validate(&self) -> Result<(), GameError> {
+        let invalid = |rule, reason| Err(GameError::InvalidRule { rule, reason });
+        if !(0..=1_000_000).contains(&self.gold) {
+            return invalid("Starting gold", "must be between 0 and 1,000,000");
+        }
+        if !(0..=1_000_000).contains(&self.food) {
+            return invalid("Starting food", "must be between 0 and 1,000,000");
+        }
+        if !(1..=100_000).contains(&self.citizens) {
+            return invalid("Starting citizens", "must be between 1 and 100,000");
+        }
+        if !(0..=100).contains(&self.farms) {
+            return invalid("Starting farms", "must be between 0 and 100");
+        }
+        if self.start_year >= self.end_year {
+            return invalid("The start year", "must be before the end year");
+        }
+        if self.event_percent > 100 {
+            return invalid("Event frequency", "must be between 0 and 100%");
+        }
+        if self.trade_limit <= 0 {
+            return invalid("The trade limit", "must be positive");
+        }
+        Ok(())
+    }
+
+    /// One line for the high-score table and game-over screen
+    pub fn summary(&self) -> String {
+        format!(
+            "{} gold, {} food, {} citizens, {} farms, {}–{}{}, {}% events, trade limit {}",
+            self.gold,
+            self.food,
+            self.citizens,
+            self.farms,
+            self.start_year,
+            self.end_year,
+            if self.year_limit { "" } else { " (no limit)" },
+            self.event_percent,
+            self.trade_limit
+        )
+    }
 }
 
 impl GameState {
     pub fn new(user_name: String, user_sex: Gender, user_difficulty: Difficulty) -> Self {
+        Self::with_seed(user_name, user_sex, user_difficulty, random_seed())
+    }
+
+    /// Starts a game whose every random roll is determined by `seed`
+    pub fn with_seed(
+        user_name: String,
+        user_sex: Gender,
+        user_difficulty: Difficulty,
+        seed: u64,
+    ) -> Self {
+        let balance = balance::current();
+        let start = &balance.start;
+        let prices = &balance.prices;
         let mut state = Self {
             user_name,
             user_sex,
             user_difficulty,
-            year: 1440,
+            custom_rules: None,
+            year: start.year,
             round: 0,
-            gold: 2000,
-            food_quantity: 2500,
-            man_quantity: 1000,
+            gold: start.gold,
+            food_quantity: start.food,
+            man_quantity: start.citizens,
             soldier_quantity: 0,
             weapon_quantity: 0,
             iron_quantity: 0,
+            armor_quantity: 0,
+            armored_soldier_quantity: 0,
             castle_level: 0,
-            farm_quantity: 1,
+            farm_quantity: start.farms,
             mine_quantity: 0,
             smithy_quantity: 0,
             market_quantity: 0,
-            price_for_castle: 5000,
-            price_for_farm: 1000,
-            price_for_mine: 3000,
-            price_for_smithy: 3000,
-            price_for_market: 2000,
-            price_for_food: 40,
-            price_for_armor: 60,
-            price_for_weapon: 150,
-            soldier_price: 100,
-            price_for_food_rate_constant: 40,
-            price_for_armor_rate_constant: 60,
-            price_for_weapon_rate_constant: 150,
-            taxes_level: 3,
-            food_supply: 3,
-            popularity_percent: 72,
-            previous_popularity_percent: 72,
+            building_condition: BuildingCondition::default(),
+            price_for_castle: prices.castle,
+            price_for_farm: prices.farm,
+            price_for_mine: prices.mine,
+            price_for_smithy: prices.smithy,
+            price_for_market: prices.market,
+            price_for_food: prices.food as i32,
+            price_for_iron: prices.iron as i32,
+            price_for_armor: prices.armor as i32,
+            price_for_weapon: prices.weapon as i32,
+            soldier_price: prices.soldier,
+            price_for_food_rate_constant: prices.food,
+            price_for_iron_rate_constant: prices.iron,
+            price_for_armor_rate_constant: prices.armor,
+            price_for_weapon_rate_constant: prices.weapon,
+            taxes_level: start.taxes_level,
+            food_supply: start.food_supply,
+            popularity_percent: start.popularity,
+            previous_popularity_percent: start.popularity,
+            morale: balance.army.morale,
             grade: 0,
             was_grade_up_before: false,
             is_castle_upgrade_in_this_round: false,
+            has_raided_this_round: false,
             taxes_value: 0,
             market_place_value: 0,
             harvest_value: 0,
             harvest_percent: 100,
             last_event_title: None,
             last_event_description: None,
+            last_report: None,
+            last_battle: None,
+            pending_event: None,
+            event_history: BTreeMap::new(),
+            chronicle: Vec::new(),
+            history: Vec::new(),
             is_game_ended: false,
             is_won: false,
-            trade_limit: 20000,
+            is_score_recorded: false,
+            trade_limit: start.trade_limit,
+            seed,
+            rng: rng_from_seed(seed),
         };
 
         state.apply_difficulty_modifier();
+        state.begin_chronicle();
+        state.begin_history();
+        state
+    }
+
+    /// Starts a `Custom` game with the player's own starting conditions
+    pub fn with_rules(user_name: String, user_sex: Gender, rules: CustomRules, seed: u64) -> Self {
+        let mut state = Self::with_seed(user_name, user_sex, Difficulty::Custom, seed);
+        state.custom_rules = Some(rules);
+        state.gold = rules.gold;
+        state.food_quantity = rules.food;
+        state.man_quantity = rules.citizens;
+        state.farm_quantity = rules.farms;
+        state.year = rules.start_year;
+        state.trade_limit = rules.trade_limit;
+        state.begin_chronicle();
+        state.begin_history();
         state
     }
 
     fn apply_difficulty_modifier(&mut self) {
-        match self.user_difficulty {
-            Difficulty::Easy => {
-                // No modifications for easy
-            }
-            Difficulty::Medium => {
-                self.food_quantity -= 800;
-                self.man_quantity -= 250;
-                self.popularity_percent -= 5;
-                self.gold -= 500;
-                self.year += 5;
-            }
-            Difficulty::Hard => {
-                self.food_quantity -= 1600;
-                self.man_quantity -= 500;
-                self.popularity_percent -= 10;
-                self.gold -= 1000;
-                self.farm_quantity -= 1;
-                self.year += 10;
-            }
-        }
+        let Some(modifier) = balance::current()
+            .difficulty
+            .for_difficulty(self.user_difficulty)
+        else {
+            return;
+        };
+        self.food_quantity += modifier.food;
+        self.man_quantity += modifier.citizens;
+        self.popularity_percent += modifier.popularity;
+        self.gold += modifier.gold;
+        self.farm_quantity += modifier.farms;
+        self.year += modifier.years;
     }
 
     pub fn get_grade_title(&self) -> &'static str {
+        self.title_for_grade(self.grade)
+    }
+
+    /// The player's title at `grade`
+    pub fn title_for_grade(&self, grade: u8) -> &'static str {
         let grades = match self.user_sex {
             Gender::Female => [
                 "Baroness", "Countess", "Duchess", "Princess", "Queen", "Empress",
             ],
             Gender::Male => ["Baron", "Count", "Duke", "Prince", "King", "Emperor"],
         };
-        grades[self.grade as usize]
+        grades[grade.min(5) as usize]
     }
 
     pub fn get_next_grade_title(&self) -> &'static str {
@@ -214,134 +429,207 @@ impl GameState {
 
     pub fn change_popularity(&mut self, amount: i8) -> i8 {
         self.popularity_percent += amount;
-        if self.popularity_percent < 0 {
-            self.popularity_percent = 0;
-        } else if self.popularity_percent > 100 {
-            self.popularity_percent = 100;
-        }
+        self.popularity_percent = self.popularity_percent.clamp(0, 100);
         amount.abs()
     }
 
+    /// Soldiers lost this way take their share of the armored soldiers with them
     pub fn change_soldiers(&mut self, amount: i16) -> i16 {
+        let before = self.soldier_quantity;
         self.soldier_quantity += amount;
-        if self.soldier_quantity < 0 {
+        let result = if self.soldier_quantity < 0 {
             let deficit = -self.soldier_quantity;
             self.soldier_quantity = 0;
             deficit
         } else {
             amount.abs()
+        };
+        if amount < 0 && before > 0 {
+            self.armored_soldier_quantity = (self.armored_soldier_quantity as i32
+                * self.soldier_quantity as i32
+                / before as i32) as i16;
+        }
+        result
+    }
+
+    /// How many of `loss` soldiers really fall in a fight. Armored soldiers take their
+    /// share of the losses, but only half of them are killed.
+    pub fn battle_losses(&self, loss: i16) -> i16 {
+        let loss = loss.min(self.soldier_quantity);
+        if self.soldier_quantity == 0 {
+            return loss;
         }
+        let armored =
+            loss as i32 * self.armored_soldier_quantity as i32 / self.soldier_quantity as i32;
+        loss - (armored / 2) as i16
     }
 
     pub fn can_build_farm(&self) -> bool {
-        self.gold >= self.price_for_farm as i32
+        self.gold >= self.price_for_farm
     }
 
     pub fn can_build_mine(&self) -> bool {
-        self.gold >= self.price_for_mine as i32
+        self.gold >= self.price_for_mine
     }
 
     pub fn can_build_smithy(&self) -> bool {
-        self.gold >= self.price_for_smithy as i32
+        self.gold >= self.price_for_smithy
     }
 
     pub fn can_build_market(&self) -> bool {
-        self.gold >= self.price_for_market as i32
+        self.gold >= self.price_for_market
+    }
+
+    pub fn can_demolish_farm(&self) -> bool {
+        self.farm_quantity > 0
+    }
+
+    pub fn can_demolish_mine(&self) -> bool {
+        self.mine_quantity > 0
+    }
+
+    pub fn can_demolish_smithy(&self) -> bool {
+        self.smithy_quantity > 0
+    }
+
+    pub fn can_demolish_market(&self) -> bool {
+        self.market_quantity > 0
     }
 
     pub fn can_upgrade_castle(&self) -> bool {
         self.castle_level < 8
-            && self.gold >= self.price_for_castle as i32
+            && self.gold >= self.price_for_castle
             && !self.is_castle_upgrade_in_this_round
     }
 
-    pub fn build_farm(&mut self) -> Result<(), String> {
+    pub fn build_farm(&mut self) -> Result<(), GameError> {
         if !self.can_build_farm() {
-            return Err("Not enough gold!".to_string());
+            return Err(GameError::InsufficientGold {
+                cost: self.price_for_farm,
+            });
         }
-        self.gold -= self.price_for_farm as i32;
-        self.farm_quantity += 1;
+        self.gold -= self.price_for_farm;
+        self.add_building(Building::Farm);
         Ok(())
     }
 
-    pub fn build_mine(&mut self) -> Result<(), String> {
+    pub fn build_mine(&mut self) -> Result<(), GameError> {
         if !self.can_build_mine() {
-            return Err("Not enough gold!".to_string());
+            return Err(GameError::InsufficientGold {
+                cost: self.price_for_mine,
+            });
         }
-        self.gold -= self.price_for_mine as i32;
-        self.mine_quantity += 1;
+        self.gold -= self.price_for_mine;
+        self.add_building(Building::Mine);
         Ok(())
     }
 
-    pub fn build_smithy(&mut self) -> Result<(), String> {
+    pub fn build_smithy(&mut self) -> Result<(), GameError> {
         if !self.can_build_smithy() {
-            return Err("Not enough gold!".to_string());
+            return Err(GameError::InsufficientGold {
+                cost: self.price_for_smithy,
+            });
         }
-        self.gold -= self.price_for_smithy as i32;
-        self.smithy_quantity += 1;
+        self.gold -= self.price_for_smithy;
+        self.add_building(Building::Smithy);
         Ok(())
     }
 
-    pub fn build_market(&mut self) -> Result<(), String> {
+    pub fn build_market(&mut self) -> Result<(), GameError> {
         if !self.can_build_market() {
-            return Err("Not enough gold!".to_string());
+            return Err(GameError::InsufficientGold {
+                cost: self.price_for_market,
+            });
+        }
+        self.gold -= self.price_for_market;
+        self.add_building(Building::Market);
+        Ok(())
+    }
+
+    pub fn demolish_farm(&mut self) -> Result<(), GameError> {
+        if !self.can_demolish_farm() {
+            return Err(GameError::NothingToDemolish);
+        }
+        self.gold += self.demolish_refund(Building::Farm);
+        self.remove_building(Building::Farm);
+        Ok(())
+    }
+
+    pub fn demolish_mine(&mut self) -> Result<(), GameError> {
+        if !self.can_demolish_mine() {
+            return Err(GameError::NothingToDemolish);
+        }
+        self.gold += self.demolish_refund(Building::Mine);
+        self.remove_building(Building::Mine);
+        Ok(())
+    }
+
+    pub fn demolish_smithy(&mut self) -> Result<(), GameError> {
+        if !self.can_demolish_smithy() {
+            return Err(GameError::NothingToDemolish);
+        }
+        self.gold += self.demolish_refund(Building::Smithy);
+        self.remove_building(Building::Smithy);
+        Ok(())
+    }
+
+    pub fn demolish_market(&mut self) -> Result<(), GameError> {
+        if !self.can_demolish_market() {
+            return Err(GameError::NothingToDemolish);
         }
-        self.gold -= self.price_for_market as i32;
-        self.market_quantity += 1;
+        self.gold += self.demolish_refund(Building::Market);
+        self.remove_building(Building::Market);
         Ok(())
     }
 
-    pub fn upgrade_castle(&mut self) -> Result<(), String> {
-        if !self.can_upgrade_castle() {
-            return Err("Cannot upgrade castle!".to_string());
+    pub fn upgrade_castle(&mut self) -> Result<(), GameError> {
+        if self.castle_level >= 8 {
+            return Err(GameError::CastleAtMaxLevel);
+        }
+        if self.is_castle_upgrade_in_this_round {
+            return Err(GameError::CastleAlreadyUpgraded);
+        }
+        if self.gold < self.price_for_castle {
+            return Err(GameError::InsufficientGold {
+                cost: self.price_for_castle,
+            });
         }
-        self.gold -= self.price_for_castle as i32;
+        self.gold -= self.price_for_castle;
         self.castle_level += 1;
         self.is_castle_upgrade_in_this_round = true;
+        self.chronicle_entry(
+            self.year,
+            ChronicleKind::Castle,
+            format!("Castle level {}", self.castle_level),
+            format!("The castle is extended to level {}.", self.castle_level),
+            vec![format!("-{} gold", self.price_for_castle)],
+        );
         Ok(())
     }
 
     pub fn get_grade_requirements(&self) -> Vec<(String, i32, i32)> {
-        let requirements = match self.grade {
-            0 => vec![
-                ("Citizens".to_string(), self.man_quantity, 1400),
-                ("Popularity".to_string(), self.popularity_percent as i32, 65),
-                ("Castle Level".to_string(), self.castle_level as i32, 0),
-                ("Soldiers".to_string(), self.soldier_quantity as i32, 0),
-                ("Gold".to_string(), self.gold, 0),
-            ],
-            1 => vec![
-                ("Citizens".to_string(), self.man_quantity, 2000),
-                ("Popularity".to_string(), self.popularity_percent as i32, 70),
-                ("Castle Level".to_string(), self.castle_level as i32, 1),
-                ("Soldiers".to_string(), self.soldier_quantity as i32, 10),
-                ("Gold".to_string(), self.gold, 0),
-            ],
-            2 => vec![
-                ("Citizens".to_string(), self.man_quantity, 3000),
-                ("Popularity".to_string(), self.popularity_percent as i32, 75),
-                ("Castle Level".to_string(), self.castle_level as i32, 2),
-                ("Soldiers".to_string(), self.soldier_quantity as i32, 25),
-                ("Gold".to_string(), self.gold, 0),
-            ],
-            3 => vec![
-                ("Citizens".to_string(), self.man_quantity, 5000),
-                ("Popularity".to_string(), self.popularity_percent as i32, 80),
-                ("Castle Level".to_string(), self.castle_level as i32, 6),
-                ("Soldiers".to_string(), self.soldier_quantity as i32, 200),
-                ("Gold".to_string(), self.gold, 100000),
-            ],
-            4 => vec![
-                ("Citizens".to_string(), self.man_quantity, 10000),
-                ("Popularity".to_string(), self.popularity_percent as i32, 90),
-                ("Castle Level".to_string(), self.castle_level as i32, 8),
-                ("Soldiers".to_string(), self.soldier_quantity as i32, 500),
-                ("Gold".to_string(), self.gold, 1000000),
-            ],
-            _ => vec![],
+        let Some(required) = balance::current().grades.get(self.grade as usize) else {
+            return vec![];
         };
-
-        requirements
+        vec![
+            ("Citizens".to_string(), self.man_quantity, required.citizens),
+            (
+                "Popularity".to_string(),
+                self.popularity_percent as i32,
+                required.popularity,
+            ),
+            (
+                "Castle Level".to_string(),
+                self.castle_level as i32,
+                required.castle_level,
+            ),
+            (
+                "Soldiers".to_string(),
+                self.soldier_quantity as i32,
+                required.soldiers,
+            ),
+            ("Gold".to_string(), self.gold, required.gold),
+        ]
     }
 
     pub fn check_can_advance_grade(&self) -> bool {
@@ -362,8 +650,9 @@ impl GameState {
         if self.castle_level < 8 {
             self.is_castle_upgrade_in_this_round = false;
         }
+        self.has_raided_this_round = false;
 
-        if self.year > 1500 {
+        if self.has_year_limit() && self.year > self.end_year() {
             self.is_game_ended = true;
             self.is_won = false;
             return false;
@@ -389,15 +678,42 @@ impl GameState {
         false
     }
 
+    /// The last year of the reign
+    pub fn end_year(&self) -> u16 {
+        match self.custom_rules {
+            Some(rules) => rules.end_year,
+            None => balance::current().start.end_year,
+        }
+    }
+
+    pub fn has_year_limit(&self) -> bool {
+        self.custom_rules.is_none_or(|rules| rules.year_limit)
+    }
+
+    /// Chance that a random event happens at the end of a round
+    pub fn event_chance(&self) -> f32 {
+        match self.custom_rules {
+            Some(rules) => rules.event_percent as f32 / 100.0,
+            None => balance::current().events.chance,
+        }
+    }
+
+    /// Notes that an event happened this round
+    pub fn record_event(&mut self, id: &str) {
+        let record = self.event_history.entry(id.to_string()).or_default();
+        record.occurrences = record.occurrences.saturating_add(1);
+        record.last_round = self.round;
+    }
+
     pub fn calculate_score(&self) -> i32 {
         if !self.is_won {
             return 0;
         }
 
         let mut score = 0;
-        score += (1501 - self.year as i32) * 100;
+        score += ((self.end_year() as i32 + 1 - self.year as i32) * 100).max(0);
         score += match self.user_difficulty {
-            Difficulty::Easy => 0,
+            Difficulty::Easy | Difficulty::Custom => 0,
             Difficulty::Medium => 1200,
             Difficulty::Hard => 2400,
         };
@@ -406,14 +722,18 @@ impl GameState {
         score
     }
 
-    pub fn buy_food(&mut self, quantity: i32) -> Result<(), String> {
+    pub fn buy_food(&mut self, quantity: i32) -> Result<(), GameError> {
+        if quantity <= 0 {
+            return Err(GameError::InvalidQuantity);
+        }
+
         if self.market_quantity == 0 {
-            return Err("No markets available!".to_string());
+            return Err(GameError::NoMarkets);
         }
 
-        let cost = (quantity / 100) * self.price_for_food as i32;
+        let cost = (quantity / 100) * self.price_for_food;
         if self.gold < cost {
-            return Err("Not enough gold!".to_string());
+            return Err(GameError::InsufficientGold { cost });
         }
 
         self.gold -= cost;
@@ -421,33 +741,43 @@ impl GameState {
         Ok(())
     }
 
-    pub fn sell_food(&mut self, quantity: i32) -> Result<(), String> {
+    pub fn sell_food(&mut self, quantity: i32) -> Result<(), GameError> {
+        if quantity <= 0 {
+            return Err(GameError::InvalidQuantity);
+        }
+
         if self.market_quantity == 0 {
-            return Err("No markets available!".to_string());
+            return Err(GameError::NoMarkets);
         }
 
         if self.food_quantity < quantity {
-            return Err("Not enough food!".to_string());
+            return Err(GameError::NotEnoughFood);
         }
 
-        let price = (quantity / 100) * self.price_for_food as i32;
+        let price = (quantity / 100) * self.price_for_food;
         self.food_quantity -= quantity;
         self.gold += price;
         Ok(())
     }
 
-    pub fn buy_iron(&mut self, quantity: i16) -> Result<(), String> {
-        if self.market_quantity <= 4 {
-            return Err("Need more than 4 markets to trade iron!".to_string());
+    pub fn buy_iron(&mut self, quantity: i16) -> Result<(), GameError> {
+        if quantity <= 0 {
+            return Err(GameError::InvalidQuantity);
+        }
+
+        if self.market_quantity < IRON_TRADE_MARKETS {
+            return Err(GameError::MarketTierTooLow {
+                required: IRON_TRADE_MARKETS,
+            });
         }
 
-        let cost = quantity as i32 * self.price_for_armor as i32;
+        let cost = quantity as i32 * self.price_for_iron;
         if self.gold < cost {
-            return Err("Not enough gold!".to_string());
+            return Err(GameError::InsufficientGold { cost });
         }
 
         if (self.iron_quantity as i32 + quantity as i32) > self.trade_limit as i32 {
-            return Err("Trade limit reached!".to_string());
+            return Err(GameError::TradeLimit);
         }
 
         self.gold -= cost;
@@ -455,33 +785,45 @@ impl GameState {
         Ok(())
     }
 
-    pub fn sell_iron(&mut self, quantity: i16) -> Result<(), String> {
-        if self.market_quantity <= 4 {
-            return Err("Need more than 4 markets to trade iron!".to_string());
+    pub fn sell_iron(&mut self, quantity: i16) -> Result<(), GameError> {
+        if quantity <= 0 {
+            return Err(GameError::InvalidQuantity);
+        }
+
+        if self.market_quantity < IRON_TRADE_MARKETS {
+            return Err(GameError::MarketTierTooLow {
+                required: IRON_TRADE_MARKETS,
+            });
         }
 
         if self.iron_quantity < quantity {
-            return Err("Not enough iron!".to_string());
+            return Err(GameError::NotEnoughIron);
         }
 
-        let cost = quantity as i32 * self.price_for_armor as i32;
+        let cost = quantity as i32 * self.price_for_iron;
         self.gold += cost;
         self.iron_quantity -= quantity;
         Ok(())
     }
 
-    pub fn buy_weapons(&mut self, quantity: i16) -> Result<(), String> {
-        if self.market_quantity <= 9 {
-            return Err("Need more than 9 markets to trade weapons!".to_string());
+    pub fn buy_weapons(&mut self, quantity: i16) -> Result<(), GameError> {
+        if quantity <= 0 {
+            return Err(GameError::InvalidQuantity);
+        }
+
+        if self.market_quantity < WEAPON_TRADE_MARKETS {
+            return Err(GameError::MarketTierTooLow {
+                required: WEAPON_TRADE_MARKETS,
+            });
         }
 
-        let cost = quantity as i32 * self.price_for_weapon as i32;
+        let cost = quantity as i32 * self.price_for_weapon;
         if self.gold < cost {
-            return Err("Not enough gold!".to_string());
+            return Err(GameError::InsufficientGold { cost });
         }
 
         if (self.weapon_quantity as i32 + quantity as i32) > self.trade_limit as i32 {
-            return Err("Trade limit reached!".to_string());
+            return Err(GameError::TradeLimit);
         }
 
         self.gold -= cost;
@@ -489,70 +831,138 @@ impl GameState {
         Ok(())
     }
 
-    pub fn sell_weapons(&mut self, quantity: i16) -> Result<(), String> {
-        if self.market_quantity <= 9 {
-            return Err("Need more than 9 markets to trade weapons!".to_string());
+    pub fn sell_weapons(&mut self, quantity: i16) -> Result<(), GameError> {
+        if quantity <= 0 {
+            return Err(GameError::InvalidQuantity);
+        }
+
+        if self.market_quantity < WEAPON_TRADE_MARKETS {
+            return Err(GameError::MarketTierTooLow {
+                required: WEAPON_TRADE_MARKETS,
+            });
         }
 
         if self.weapon_quantity < quantity {
-            return Err("Not enough weapons!".to_string());
+            return Err(GameError::NotEnoughWeapons);
         }
 
-        let cost = quantity as i32 * self.price_for_weapon as i32;
+        let cost = quantity as i32 * self.price_for_weapon;
         self.gold += cost;
         self.weapon_quantity -= quantity;
         Ok(())
     }
 
-    pub fn recruit_soldiers(&mut self, quantity: i16) -> Result<(), String> {
+    pub fn buy_armor(&mut self, quantity: i16) -> Result<(), GameError> {
+        if quantity <= 0 {
+            return Err(GameError::InvalidQuantity);
+        }
+
+        if self.market_quantity < ARMOR_TRADE_MARKETS {
+            return Err(GameError::MarketTierTooLow {
+                required: ARMOR_TRADE_MARKETS,
+            });
+        }
+
+        let cost = quantity as i32 * self.price_for_armor;
+        if self.gold < cost {
+            return Err(GameError::InsufficientGold { cost });
+        }
+
+        if (self.armor_quantity as i32 + quantity as i32) > self.trade_limit as i32 {
+            return Err(GameError::TradeLimit);
+        }
+
+        self.gold -= cost;
+        self.armor_quantity += quantity;
+        Ok(())
+    }
+
+    pub fn sell_armor(&mut self, quantity: i16) -> Result<(), GameError> {
         if quantity <= 0 {
-            return Err("Quantity must be positive!".to_string());
+            return Err(GameError::InvalidQuantity);
         }
 
-        let cost = quantity as i32 * self.soldier_price as i32;
+        if self.market_quantity < ARMOR_TRADE_MARKETS {
+            return Err(GameError::MarketTierTooLow {
+                required: ARMOR_TRADE_MARKETS,
+            });
+        }
+
+        if self.armor_quantity < quantity {
+            return Err(GameError::NotEnoughArmor);
+        }
+
+        let cost = quantity as i32 * self.price_for_armor;
+        self.gold += cost;
+        self.armor_quantity -= quantity;
+        Ok(())
+    }
+
+    /// Recruits soldiers with a weapon each and, if `armored`, an armor each
+    pub fn recruit_soldiers(&mut self, quantity: i16, armored: bool) -> Result<(), GameError> {
+        if quantity <= 0 {
+            return Err(GameError::InvalidQuantity);
+        }
+
+        let cost = quantity as i32 * self.soldier_price;
         if self.gold < cost {
-            return Err("Not enough gold!".to_string());
+            return Err(GameError::InsufficientGold { cost });
         }
 
         if self.weapon_quantity < quantity {
-            return Err("Not enough weapons!".to_string());
+            return Err(GameError::NotEnoughWeapons);
+        }
+
+        if armored && self.armor_quantity < quantity {
+            return Err(GameError::NotEnoughArmor);
         }
 
-        if self.man_quantity - 200 < self.soldier_quantity as i32 + quantity as i32 {
-            return Err("Not enough citizens! (Must keep at least 200 citizens)".to_string());
+        if self.man_quantity - MIN_CITIZENS < self.soldier_quantity as i32 + quantity as i32 {
+            return Err(GameError::NotEnoughCitizens {
+                minimum: MIN_CITIZENS,
+            });
         }
 
         if (self.soldier_quantity as i32 + quantity as i32) > self.trade_limit as i32 {
-            return Err("Trade limit reached!".to_string());
+            return Err(GameError::TradeLimit);
         }
 
         self.gold -= cost;
         self.weapon_quantity -= quantity;
         self.man_quantity -= quantity as i32;
         self.soldier_quantity += quantity;
+        if armored {
+            self.armor_quantity -= quantity;
+            self.armored_soldier_quantity += quantity;
+        }
         Ok(())
     }
 
-    pub fn discharge_soldiers(&mut self, quantity: i16) -> Result<(), String> {
+    pub fn discharge_soldiers(&mut self, quantity: i16) -> Result<(), GameError> {
         if quantity <= 0 {
-            return Err("Quantity must be positive!".to_string());
+            return Err(GameError::InvalidQuantity);
         }
 
         if self.soldier_quantity < quantity {
-            return Err("Not enough soldiers!".to_string());
+            return Err(GameError::NotEnoughSoldiers);
         }
 
-        let refund = quantity as i32 * self.soldier_price as i32;
+        let refund = quantity as i32 * self.soldier_price;
+        // Soldiers without armor leave first; the others hand in their armor
+        let unarmored = self.soldier_quantity - self.armored_soldier_quantity;
+        let armored = (quantity - unarmored).max(0);
 
         self.gold += refund;
         self.weapon_quantity += quantity;
+        self.armor_quantity += armored;
+        self.armored_soldier_quantity -= armored;
         self.man_quantity += quantity as i32;
         self.soldier_quantity -= quantity;
         Ok(())
     }
 
     pub fn can_afford_soldier(&self) -> bool {
-        self.gold >= self.soldier_price as i32
+        self.gold >= self.soldier_price
     }
 }
 
@@ -561,3 +971,45 @@ impl Default for GameState {
         Self::new("Player".to_string(), Gender::Male, Difficulty::Easy)
     }
 }
+
+/// A seeded easy game for tests
+#[cfg(test)]
+pub fn test_game(seed: u64) -> GameState {
+    GameState::with_seed("Ada".to_string(), Gender::Female, Difficulty::Easy, seed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game() -> GameState {
+        GameState::with_seed("Test".to_string(), Gender::Male, Difficulty::Easy, 1)
+    }
+
+    #[test]
+    fn test_armor_saves_soldiers() {
+        let mut state = game();
+        state.gold = 10_000;
+        state.weapon_quantity = 20;
+        state.armor_quantity = 10;
+        state.recruit_soldiers(10, true).unwrap();
+        state.recruit_soldiers(10, false).unwrap();
+        assert_eq!(
+            (state.soldier_quantity, state.armored_soldier_quantity),
+            (20, 10)
+        );
+        assert_eq!(state.armor_quantity, 0);
+
+        // Half of the 10 armored soldiers among 20 losses survive
+        assert_eq!(state.battle_losses(20), 15);
+
+        state.change_soldiers(-10);
+        assert_eq!(state.armored_soldier_quantity, 5);
+        state.discharge_soldiers(8).unwrap();
+        assert_eq!(
+            (state.soldier_quantity, state.armored_soldier_quantity),
+            (2, 2)
+        );
+        assert_eq!(state.armor_quantity, 3);
+    }
+}
@@ -0,0 +1,253 @@
+//! Persistent high-score table
+//! Every finished game is appended to a JSON file; the highscores page filters and pages through it.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::game::{CustomRules, Difficulty, GameState};
+
+/// Number of entries shown per page on `/highscores`
+pub const PAGE_SIZE: usize = 10;
+
+/// One finished game
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HighscoreEntry {
+    pub player_name: String,
+    pub score: i32,
+    pub difficulty: Difficulty,
+    /// The rules of a `Custom` game
+    #[serde(default)]
+    pub rules: Option<CustomRules>,
+    pub final_year: u16,
+    pub final_grade: String,
+    pub won: bool,
+    pub recorded_at: u64,
+}
+
+impl HighscoreEntry {
+    pub fn from_game(game: &GameState) -> Self {
+        Self {
+            player_name: game.user_name.clone(),
+            score: game.calculate_score(),
+            difficulty: game.user_difficulty,
+            rules: game.custom_rules,
+            final_year: game.year,
+            final_grade: game.get_grade_title().to_string(),
+            won: game.is_won,
+            recorded_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        }
+    }
+}
+
+/// A ranked entry as shown in the table
+#[derive(Debug, Clone)]
+pub struct RankedEntry {
+    pub rank: usize,
+    pub entry: HighscoreEntry,
+}
+
+/// One page of the (optionally filtered) high-score table
+#[derive(Debug, Clone)]
+pub struct HighscorePage {
+    pub entries: Vec<RankedEntry>,
+    pub page: usize,
+    pub total_pages: usize,
+}
+
+impl HighscorePage {
+    pub fn has_previous(&self) -> bool {
+        self.page > 1
+    }
+
+    pub fn has_next(&self) -> bool {
+        self.page < self.total_pages
+    }
+}
+
+/// JSON-file-backed high-score store
+#[derive(Debug, Clone)]
+pub struct HighscoreStore {
+    path: PathBuf,
+    // Serializes read-modify-write cycles between concurrent requests
+    lock: Arc<Mutex<()>>,
+}
+
+impl HighscoreStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub fn record(&self, entry: HighscoreEntry) -> io::Result<()> {
+        let _guard = self.lock.lock().unwrap();
+        let mut entries = self.read_entries()?;
+        entries.push(entry);
+
+        if let Some(parent) = self.path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_vec_pretty(&entries)?)?;
+        fs::rename(&tmp_path, &self.path)
+    }
+
+    /// Records a finished game exactly once, however often it is asked to
+    pub fn record_finished(&self, game: &mut GameState) {
+        if !game.is_game_ended || game.is_score_recorded {
+            return;
+        }
+        match self.record(HighscoreEntry::from_game(game)) {
+            Ok(()) => game.is_score_recorded = true,
+            Err(err) => tracing::warn!("Could not record highscore: {}", err),
+        }
+    }
+
+    /// All entries, best first: higher score, then earlier final year, then older record
+    pub fn ranked(&self) -> Vec<HighscoreEntry> {
+        let _guard = self.lock.lock().unwrap();
+        let mut entries = self.read_entries().unwrap_or_else(|err| {
+            tracing::warn!("Could not read highscores: {}", err);
+            Vec::new()
+        });
+        entries.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then(a.final_year.cmp(&b.final_year))
+                .then(a.recorded_at.cmp(&b.recorded_at))
+        });
+        entries
+    }
+
+    /// One page of entries, optionally restricted to a difficulty and/or a player.
+    /// Ranks are positions within the filtered table.
+    pub fn page(
+        &self,
+        difficulty: Option<Difficulty>,
+        player: Option<&str>,
+        page: usize,
+    ) -> HighscorePage {
+        let filtered: Vec<HighscoreEntry> = self
+            .ranked()
+            .into_iter()
+            .filter(|entry| difficulty.is_none_or(|d| entry.difficulty == d))
+            .filter(|entry| player.is_none_or(|p| entry.player_name.eq_ignore_ascii_case(p)))
+            .collect();
+
+        let total_pages = filtered.len().div_ceil(PAGE_SIZE).max(1);
+        let page = page.clamp(1, total_pages);
+        let entries = filtered
+            .into_iter()
+            .enumerate()
+            .skip((page - 1) * PAGE_SIZE)
+            .take(PAGE_SIZE)
+            .map(|(index, entry)| RankedEntry {
+                rank: index + 1,
+                entry,
+            })
+            .collect();
+
+        HighscorePage {
+            entries,
+            page,
+            total_pages,
+        }
+    }
+
+    /// The best game of `player` on each difficulty they have finished
+    pub fn personal_bests(&self, player: &str) -> Vec<HighscoreEntry> {
+        let mut bests: Vec<HighscoreEntry> = Vec::new();
+        for entry in self
+            .ranked()
+            .into_iter()
+            .filter(|entry| entry.player_name.eq_ignore_ascii_case(player))
+        {
+            if !bests.iter().any(|best| best.difficulty == entry.difficulty) {
+                bests.push(entry);
+            }
+        }
+        bests
+    }
+
+    fn read_entries(&self) -> io::Result<Vec<HighscoreEntry>> {
+        match fs::read(&self.path) {
+            Ok(data) => Ok(serde_json::from_slice(&data)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store(name: &str) -> HighscoreStore {
+        let path = std::env::temp_dir().join(format!(
+            "rust-emperor-highscores-{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        HighscoreStore::new(path)
+    }
+
+    fn entry(name: &str, score: i32, difficulty: Difficulty) -> HighscoreEntry {
+        HighscoreEntry {
+            player_name: name.to_string(),
+            score,
+            difficulty,
+            rules: None,
+            final_year: 1480,
+            final_grade: "Emperor".to_string(),
+            won: score > 0,
+            recorded_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_filter_and_rank() {
+        let store = temp_store("filter");
+        store.record(entry("Ann", 500, Difficulty::Easy)).unwrap();
+        store.record(entry("Bob", 900, Difficulty::Hard)).unwrap();
+        store.record(entry("Ann", 700, Difficulty::Hard)).unwrap();
+
+        let all = store.page(None, None, 1);
+        let scores: Vec<_> = all.entries.iter().map(|r| r.entry.score).collect();
+        assert_eq!(scores, vec![900, 700, 500]);
+
+        let hard = store.page(Some(Difficulty::Hard), None, 1);
+        assert_eq!(hard.entries.len(), 2);
+        assert_eq!(hard.entries[1].rank, 2);
+
+        let bests = store.personal_bests("ann");
+        assert_eq!(bests.len(), 2);
+        assert_eq!(bests[0].score, 700);
+    }
+
+    #[test]
+    fn test_paging() {
+        let store = temp_store("paging");
+        for score in 0..(PAGE_SIZE as i32 + 3) {
+            store.record(entry("P", score, Difficulty::Easy)).unwrap();
+        }
+        let second = store.page(None, None, 2);
+        assert_eq!(second.total_pages, 2);
+        assert_eq!(second.entries.len(), 3);
+        assert_eq!(second.entries[0].rank, PAGE_SIZE + 1);
+        assert!(second.has_previous() && !second.has_next());
+
+        // Out-of-range pages are clamped
+        assert_eq!(store.page(None, None, 99).page, 2);
+    }
+}
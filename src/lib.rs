@@ -0,0 +1,5 @@
+//! Dark Emperor game logic and the number and date filters used by the templates
+//! Shared by the web server (`main.rs`) and the terminal client (`bin/rust-emperor-cli.rs`).
+
+pub mod filters;
+pub mod game;
@@ -1,14 +1,20 @@
-use axum::Router;
-use std::sync::{Arc, RwLock};
+use axum::{middleware, Router};
 use tower::ServiceBuilder;
 use tower_http::{services::ServeDir, trace::TraceLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-mod filters;
-mod game;
+mod app_state;
+mod charts;
+mod highscores;
+mod reign_report;
 mod routes;
+mod saves;
+mod session;
 
-use routes::game::SharedGameState;
+use app_state::AppState;
+use rust_emperor::game;
+use rust_emperor::game::balance::{self, Balance};
+use rust_emperor::game::{event_data, events};
 
 #[tokio::main]
 async fn main() {
@@ -21,19 +27,54 @@ async fn main() {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    // Create shared game state
-    let game_state: SharedGameState = Arc::new(RwLock::new(None));
+    // Replace the built-in balance before any game starts
+    if let Ok(path) = std::env::var("EMPEROR_BALANCE") {
+        match Balance::load(&path) {
+            Ok(loaded) => {
+                balance::install(loaded);
+                tracing::info!("Loaded balance from {}", path);
+            }
+            Err(err) => {
+                tracing::error!("{}: {}", path, err);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Add the events from the events directory before any game starts
+    let events_dir = std::env::var("EMPEROR_EVENTS_DIR").unwrap_or_else(|_| "events".to_string());
+    match event_data::load_registry(&events_dir) {
+        Ok(registry) => {
+            tracing::info!("Loaded {} events", registry.definitions().len());
+            events::install(registry);
+        }
+        Err(err) => {
+            tracing::error!("{}", err);
+            std::process::exit(1);
+        }
+    }
+
+    // Create shared application state and expire idle sessions in the background
+    let app_state = AppState::from_env();
+    tokio::spawn(session::expire_idle_sessions(app_state.sessions.clone()));
 
     // Build our application with routes
     let app = Router::new()
-        // Serve static files
-        .nest_service("/static", ServeDir::new("static"))
-        // Menu routes (no state needed)
+        // Menu routes
         .merge(routes::menu_routes())
-        // Game routes (need state)
+        // Game routes
         .merge(routes::game_routes())
+        // JSON API for scripts and bots
+        .nest("/api/v1", routes::api_routes())
+        // Resolve the caller's session for every page
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            session::session_layer::<AppState>,
+        ))
+        // Serve static files
+        .nest_service("/static", ServeDir::new("static"))
         // Add state
-        .with_state(game_state)
+        .with_state(app_state)
         // Add middleware
         .layer(ServiceBuilder::new().layer(TraceLayer::new_for_http()));
 
@@ -0,0 +1,213 @@
+//! Reign report
+//! A finished game rendered as a standalone Markdown or HTML file that can be shared.
+//! The HTML page embeds its icons, so it needs nothing from the server.
+
+use askama::Template;
+use base64::Engine;
+
+use crate::game::chronicle::{ChronicleEntry, ChronicleKind};
+use crate::game::history::RoundRecord;
+use crate::game::GameState;
+use rust_emperor::filters;
+
+/// One row of the per-year resource table
+pub struct ReportRow {
+    pub label: String,
+    pub gold: i32,
+    pub food: i32,
+    pub citizens: i32,
+    pub soldiers: i16,
+    pub popularity: i8,
+    pub title: &'static str,
+    pub event: String,
+}
+
+/// Everything both formats show
+pub struct ReignReport {
+    pub player: String,
+    pub title: &'static str,
+    pub difficulty: String,
+    pub rules: Option<String>,
+    pub seed: u64,
+    pub start_year: u16,
+    pub final_year: u16,
+    pub won: bool,
+    pub score: i32,
+    pub start: ReportRow,
+    pub rows: Vec<ReportRow>,
+    pub events: Vec<ChronicleEntry>,
+    pub promotions: Vec<ChronicleEntry>,
+}
+
+impl ReignReport {
+    pub fn from_game(game: &GameState) -> Self {
+        let row = |record: &RoundRecord| ReportRow {
+            label: if record.is_start() {
+                "Start".to_string()
+            } else {
+                record.year.to_string()
+            },
+            gold: record.gold,
+            food: record.food,
+            citizens: record.citizens,
+            soldiers: record.soldiers,
+            popularity: record.popularity,
+            title: game.title_for_grade(record.grade),
+            event: record.event.clone().unwrap_or_default(),
+        };
+        let mut rows: Vec<ReportRow> = game.history.iter().map(row).collect();
+        // Games saved before the history was kept have no start record
+        let start = if game.history.first().is_some_and(|r| r.is_start()) {
+            rows.remove(0)
+        } else {
+            row(&RoundRecord::of(game, 0, game.year))
+        };
+        let entries = |kinds: &[ChronicleKind]| {
+            game.chronicle
+                .iter()
+                .filter(|entry| kinds.contains(&entry.kind))
+                .cloned()
+                .collect()
+        };
+
+        Self {
+            player: game.user_name.clone(),
+            title: game.get_grade_title(),
+            difficulty: game.user_difficulty.to_string(),
+            rules: game.custom_rules.map(|rules| rules.summary()),
+            seed: game.seed,
+            start_year: game.history.first().map_or(game.year, |r| r.year),
+            final_year: game.year,
+            won: game.is_won,
+            score: game.calculate_score(),
+            start,
+            rows,
+            events: entries(&[
+                ChronicleKind::Event,
+                ChronicleKind::Decision,
+                ChronicleKind::Battle,
+            ]),
+            promotions: entries(&[ChronicleKind::Promotion]),
+        }
+    }
+
+    pub fn outcome(&self) -> &'static str {
+        if self.won {
+            "Won"
+        } else {
+            "Lost"
+        }
+    }
+
+    /// A file name without characters browsers or file systems may reject
+    pub fn file_name(&self, extension: &str) -> String {
+        let player: String = self
+            .player
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        format!("reign-{}-{}.{}", player, self.final_year, extension)
+    }
+
+    pub fn markdown(self) -> String {
+        MarkdownTemplate { report: self }.render().unwrap()
+    }
+
+    pub fn html(self) -> String {
+        HtmlTemplate {
+            report: self,
+            icons: Icons::new(),
+        }
+        .render()
+        .unwrap()
+    }
+}
+
+#[derive(Template)]
+#[template(path = "reign_report.md")]
+struct MarkdownTemplate {
+    report: ReignReport,
+}
+
+#[derive(Template)]
+#[template(path = "reign_report_page.html")]
+struct HtmlTemplate {
+    report: ReignReport,
+    icons: Icons,
+}
+
+/// The game's icons as data URIs
+struct Icons {
+    gold: String,
+    food: String,
+    citizens: String,
+    soldiers: String,
+    crown: String,
+    outcome_won: String,
+    outcome_lost: String,
+}
+
+impl Icons {
+    fn new() -> Self {
+        Self {
+            gold: png(include_bytes!("../static/images/deg_gold.png")),
+            food: png(include_bytes!("../static/images/deg_food.png")),
+            citizens: png(include_bytes!("../static/images/deg_man.png")),
+            soldiers: png(include_bytes!("../static/images/deg_soldier.png")),
+            crown: png(include_bytes!("../static/images/de2.png")),
+            outcome_won: png(include_bytes!("../static/images/deg_win.png")),
+            outcome_lost: png(include_bytes!("../static/images/deg_lost.png")),
+        }
+    }
+}
+
+fn png(bytes: &[u8]) -> String {
+    format!(
+        "data:image/png;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{round, Difficulty, Gender};
+
+    fn finished_game() -> GameState {
+        let mut game = GameState::with_seed(
+            "Ada | Lovelace".to_string(),
+            Gender::Female,
+            Difficulty::Easy,
+            9,
+        );
+        for _ in 0..3 {
+            round::play_seeded_round(&mut game);
+        }
+        game.is_game_ended = true;
+        game
+    }
+
+    #[test]
+    fn test_markdown_report() {
+        let game = finished_game();
+        let report = ReignReport::from_game(&game);
+        assert_eq!(report.rows.len(), 3);
+        assert_eq!(
+            report.file_name("md"),
+            format!("reign-Ada---Lovelace-{}.md", game.year)
+        );
+
+        let markdown = report.markdown();
+        assert!(markdown.starts_with("# Reign of Baroness Ada \\| Lovelace"));
+        assert!(markdown.contains("| Start |"));
+        assert!(markdown.contains("**Outcome:** Lost"));
+    }
+
+    #[test]
+    fn test_html_report_is_self_contained() {
+        let html = ReignReport::from_game(&finished_game()).html();
+        assert!(html.contains("data:image/png;base64,"));
+        assert!(!html.contains("/static/"));
+        assert!(html.contains("Ada | Lovelace"));
+    }
+}
@@ -0,0 +1,425 @@
+//! JSON API
+//! Mirrors every game action for scripts and bots. Every successful call returns the full
+//! `GameState`; failures return `{"error": "<code>", "message": "..."}` with a matching status.
+//! The game lives in the same cookie session as the HTML pages, so clients must keep cookies.
+
+use axum::{
+    async_trait,
+    extract::{rejection::JsonRejection, FromRequest, Request, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::app_state::AppState;
+use crate::game::battle;
+use crate::game::buildings::Building;
+use crate::game::error::{small_quantity, GameError};
+use crate::game::{rng, round, CustomRules, Difficulty, GameState, Gender};
+use crate::saves::AUTOSAVE_SLOT;
+use crate::session::SharedSession;
+
+type ApiResult = Result<Json<GameState>, ApiError>;
+
+/// An error response: HTTP status plus a JSON body with a machine-readable `error` code
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    body: Value,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, code: &str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: json!({ "error": code, "message": message.into() }),
+        }
+    }
+
+    fn no_active_game() -> Self {
+        Self::new(
+            StatusCode::NOT_FOUND,
+            "no_active_game",
+            "No active game. Start one with POST /api/v1/game.",
+        )
+    }
+
+    fn game_ended() -> Self {
+        Self::new(StatusCode::CONFLICT, "game_ended", "This game has ended.")
+    }
+
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "bad_request", message)
+    }
+}
+
+impl From<GameError> for ApiError {
+    fn from(err: GameError) -> Self {
+        let mut body = serde_json::to_value(err).unwrap_or_else(|_| json!({}));
+        body["message"] = Value::String(err.to_string());
+        Self {
+            status: StatusCode::UNPROCESSABLE_ENTITY,
+            body,
+        }
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::new(rejection.status(), "bad_request", rejection.body_text())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(self.body)).into_response()
+    }
+}
+
+/// `Json` extractor whose rejections use the API error format
+pub struct ApiJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ApiJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state).await?;
+        Ok(ApiJson(value))
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NewGameRequest {
+    player_name: String,
+    gender: Gender,
+    difficulty: Difficulty,
+    /// Random when missing
+    seed: Option<u64>,
+    /// Starting conditions for a `Custom` game; missing fields use the Easy values
+    rules: Option<CustomRules>,
+}
+
+#[derive(Deserialize)]
+pub struct LevelRequest {
+    level: u8,
+}
+
+#[derive(Deserialize)]
+pub struct QuantityRequest {
+    quantity: i32,
+}
+
+#[derive(Deserialize)]
+pub struct RepairRequest {
+    building: Building,
+}
+
+#[derive(Deserialize)]
+pub struct RecruitRequest {
+    quantity: i32,
+    /// Equip every recruit with armor from the stores
+    #[serde(default)]
+    armored: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ChoiceRequest {
+    /// Index into `pending_event.choices`
+    choice: usize,
+}
+
+/// Runs `action` on the caller's running game and returns the updated state
+fn with_game(
+    session: &SharedSession,
+    action: impl FnOnce(&mut GameState) -> Result<(), GameError>,
+) -> ApiResult {
+    let mut session = session.write().unwrap();
+    let game = session.game.as_mut().ok_or_else(ApiError::no_active_game)?;
+    if game.is_game_ended {
+        return Err(ApiError::game_ended());
+    }
+    action(game)?;
+    Ok(Json(game.clone()))
+}
+
+async fn new_game(
+    Extension(session): Extension<SharedSession>,
+    ApiJson(request): ApiJson<NewGameRequest>,
+) -> Result<(StatusCode, Json<GameState>), ApiError> {
+    let player_name = request.player_name.trim();
+    if player_name.is_empty() {
+        return Err(ApiError::bad_request("player_name must not be empty"));
+    }
+
+    let seed = request.seed.unwrap_or_else(rng::random_seed);
+    let state = if request.difficulty == Difficulty::Custom {
+        let rules = request.rules.unwrap_or_default();
+        rules.validate()?;
+        GameState::with_rules(player_name.to_string(), request.gender, rules, seed)
+    } else {
+        GameState::with_seed(
+            player_name.to_string(),
+            request.gender,
+            request.difficulty,
+            seed,
+        )
+    };
+    session.write().unwrap().game = Some(state.clone());
+
+    Ok((StatusCode::CREATED, Json(state)))
+}
+
+async fn get_game(Extension(session): Extension<SharedSession>) -> ApiResult {
+    let session = session.read().unwrap();
+    let game = session.game.as_ref().ok_or_else(ApiError::no_active_game)?;
+    Ok(Json(game.clone()))
+}
+
+async fn set_taxes(
+    Extension(session): Extension<SharedSession>,
+    ApiJson(request): ApiJson<LevelRequest>,
+) -> ApiResult {
+    if request.level > 5 {
+        return Err(ApiError::bad_request("level must be between 0 and 5"));
+    }
+    with_game(&session, |game| {
+        game.taxes_level = request.level;
+        Ok(())
+    })
+}
+
+async fn set_food_supply(
+    Extension(session): Extension<SharedSession>,
+    ApiJson(request): ApiJson<LevelRequest>,
+) -> ApiResult {
+    if request.level > 5 {
+        return Err(ApiError::bad_request("level must be between 0 and 5"));
+    }
+    with_game(&session, |game| {
+        game.food_supply = request.level;
+        Ok(())
+    })
+}
+
+async fn build_farm(Extension(session): Extension<SharedSession>) -> ApiResult {
+    with_game(&session, GameState::build_farm)
+}
+
+async fn build_mine(Extension(session): Extension<SharedSession>) -> ApiResult {
+    with_game(&session, GameState::build_mine)
+}
+
+async fn build_smithy(Extension(session): Extension<SharedSession>) -> ApiResult {
+    with_game(&session, GameState::build_smithy)
+}
+
+async fn build_market(Extension(session): Extension<SharedSession>) -> ApiResult {
+    with_game(&session, GameState::build_market)
+}
+
+async fn demolish_farm(Extension(session): Extension<SharedSession>) -> ApiResult {
+    with_game(&session, GameState::demolish_farm)
+}
+
+async fn demolish_mine(Extension(session): Extension<SharedSession>) -> ApiResult {
+    with_game(&session, GameState::demolish_mine)
+}
+
+async fn demolish_smithy(Extension(session): Extension<SharedSession>) -> ApiResult {
+    with_game(&session, GameState::demolish_smithy)
+}
+
+async fn demolish_market(Extension(session): Extension<SharedSession>) -> ApiResult {
+    with_game(&session, GameState::demolish_market)
+}
+
+async fn repair(
+    Extension(session): Extension<SharedSession>,
+    ApiJson(request): ApiJson<RepairRequest>,
+) -> ApiResult {
+    with_game(&session, |game| game.repair(request.building))
+}
+
+async fn upgrade_castle(Extension(session): Extension<SharedSession>) -> ApiResult {
+    with_game(&session, GameState::upgrade_castle)
+}
+
+async fn buy_food(
+    Extension(session): Extension<SharedSession>,
+    ApiJson(request): ApiJson<QuantityRequest>,
+) -> ApiResult {
+    with_game(&session, |game| game.buy_food(request.quantity))
+}
+
+async fn sell_food(
+    Extension(session): Extension<SharedSession>,
+    ApiJson(request): ApiJson<QuantityRequest>,
+) -> ApiResult {
+    with_game(&session, |game| game.sell_food(request.quantity))
+}
+
+async fn buy_iron(
+    Extension(session): Extension<SharedSession>,
+    ApiJson(request): ApiJson<QuantityRequest>,
+) -> ApiResult {
+    with_game(&session, |game| {
+        game.buy_iron(small_quantity(request.quantity)?)
+    })
+}
+
+async fn sell_iron(
+    Extension(session): Extension<SharedSession>,
+    ApiJson(request): ApiJson<QuantityRequest>,
+) -> ApiResult {
+    with_game(&session, |game| {
+        game.sell_iron(small_quantity(request.quantity)?)
+    })
+}
+
+async fn buy_weapons(
+    Extension(session): Extension<SharedSession>,
+    ApiJson(request): ApiJson<QuantityRequest>,
+) -> ApiResult {
+    with_game(&session, |game| {
+        game.buy_weapons(small_quantity(request.quantity)?)
+    })
+}
+
+async fn sell_weapons(
+    Extension(session): Extension<SharedSession>,
+    ApiJson(request): ApiJson<QuantityRequest>,
+) -> ApiResult {
+    with_game(&session, |game| {
+        game.sell_weapons(small_quantity(request.quantity)?)
+    })
+}
+
+async fn buy_armor(
+    Extension(session): Extension<SharedSession>,
+    ApiJson(request): ApiJson<QuantityRequest>,
+) -> ApiResult {
+    with_game(&session, |game| {
+        game.buy_armor(small_quantity(request.quantity)?)
+    })
+}
+
+async fn sell_armor(
+    Extension(session): Extension<SharedSession>,
+    ApiJson(request): ApiJson<QuantityRequest>,
+) -> ApiResult {
+    with_game(&session, |game| {
+        game.sell_armor(small_quantity(request.quantity)?)
+    })
+}
+
+async fn recruit_soldiers(
+    Extension(session): Extension<SharedSession>,
+    ApiJson(request): ApiJson<RecruitRequest>,
+) -> ApiResult {
+    with_game(&session, |game| {
+        game.recruit_soldiers(small_quantity(request.quantity)?, request.armored)
+    })
+}
+
+async fn discharge_soldiers(
+    Extension(session): Extension<SharedSession>,
+    ApiJson(request): ApiJson<QuantityRequest>,
+) -> ApiResult {
+    with_game(&session, |game| {
+        game.discharge_soldiers(small_quantity(request.quantity)?)
+    })
+}
+
+async fn raid(Extension(session): Extension<SharedSession>) -> ApiResult {
+    with_game(&session, |game| battle::raid(game).map(|_| ()))
+}
+
+async fn finish_round(
+    State(app): State<AppState>,
+    Extension(session): Extension<SharedSession>,
+) -> ApiResult {
+    let mut session = session.write().unwrap();
+    let owner = session.owner.clone();
+    let game = session.game.as_mut().ok_or_else(ApiError::no_active_game)?;
+    if game.is_game_ended {
+        return Err(ApiError::game_ended());
+    }
+    if game.pending_event.is_some() {
+        return Err(GameError::DecisionPending.into());
+    }
+
+    round::play_seeded_round(game);
+
+    // Record a finished game before the autosave, so the save carries the flag
+    app.highscores.record_finished(game);
+    if let Err(err) = app
+        .saves
+        .for_owner(&owner)
+        .and_then(|saves| saves.save(AUTOSAVE_SLOT, game))
+    {
+        tracing::warn!("Autosave to slot '{}' failed: {}", AUTOSAVE_SLOT, err);
+    }
+
+    Ok(Json(game.clone()))
+}
+
+async fn choose_event(
+    Extension(session): Extension<SharedSession>,
+    ApiJson(request): ApiJson<ChoiceRequest>,
+) -> ApiResult {
+    with_game(&session, |game| round::resolve_event(game, request.choice))
+}
+
+/// Routes nested under `/api/v1`
+pub fn api_routes() -> Router<AppState> {
+    Router::new()
+        .route("/game", get(get_game).post(new_game))
+        .route("/game/taxes", post(set_taxes))
+        .route("/game/food-supply", post(set_food_supply))
+        .route("/game/build-farm", post(build_farm))
+        .route("/game/build-mine", post(build_mine))
+        .route("/game/build-smithy", post(build_smithy))
+        .route("/game/build-market", post(build_market))
+        .route("/game/build/demolish-farm", post(demolish_farm))
+        .route("/game/build/demolish-mine", post(demolish_mine))
+        .route("/game/build/demolish-smithy", post(demolish_smithy))
+        .route("/game/build/demolish-market", post(demolish_market))
+        .route("/game/repair", post(repair))
+        .route("/game/upgrade-castle", post(upgrade_castle))
+        .route("/game/finish-round", post(finish_round))
+        .route("/game/event/choose", post(choose_event))
+        .route("/game/trade/buy-food", post(buy_food))
+        .route("/game/trade/sell-food", post(sell_food))
+        .route("/game/trade/buy-iron", post(buy_iron))
+        .route("/game/trade/sell-iron", post(sell_iron))
+        .route("/game/trade/buy-weapons", post(buy_weapons))
+        .route("/game/trade/sell-weapons", post(sell_weapons))
+        .route("/game/trade/buy-armor", post(buy_armor))
+        .route("/game/trade/sell-armor", post(sell_armor))
+        .route("/game/army/recruit", post(recruit_soldiers))
+        .route("/game/army/discharge", post(discharge_soldiers))
+        .route("/game/army/raid", post(raid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_game_error_body() {
+        let error = ApiError::from(GameError::InsufficientGold { cost: 500 });
+        assert_eq!(error.status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(error.body["error"], "insufficient_gold");
+        assert_eq!(error.body["cost"], 500);
+        assert!(error.body["message"].is_string());
+    }
+}
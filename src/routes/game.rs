@@ -1,28 +1,91 @@
 use askama::Template;
 use axum::{
-    extract::{Form, State},
+    extract::{Form, Query, State},
     response::{Html, IntoResponse, Redirect},
     routing::{get, post},
-    Router,
+    Extension, Router,
 };
 use serde::Deserialize;
-use std::sync::{Arc, RwLock};
 
-use crate::game::{EventGenerator, GameState};
-
-// Shared game state (in a real app, use proper session management)
-pub type SharedGameState = Arc<RwLock<Option<GameState>>>;
+use crate::app_state::AppState;
+use crate::charts::{self, LineChart};
+use crate::game::advisor::{self, Action, StrategyKind, MAX_AUTOPLAY_ROUNDS};
+use crate::game::army::Upkeep;
+use crate::game::battle::{self, ArmyStrength, BattleKind, BattleReport};
+use crate::game::buildings::Building;
+use crate::game::chronicle::ChronicleEntry;
+use crate::game::error::{small_quantity, GameError};
+use crate::game::forecast::Forecast;
+use crate::game::report::RoundReport;
+use crate::game::{round, GameState};
+use crate::saves::AUTOSAVE_SLOT;
+use crate::session::{Flash, FlashKind, SharedSession};
 
 #[derive(Template)]
 #[template(path = "game.html")]
 struct GameTemplate {
     state: GameState,
+    flash: Vec<Flash>,
+    advisor: AdvisorPanel,
+    charts: Vec<LineChart>,
+    forecast: Forecast,
+    /// Strength of the army and of the outpost a raid would meet
+    army: ArmyStrength,
+    outpost: i32,
+    /// What the soldiers are owed every round
+    upkeep: Upkeep,
+}
+
+#[derive(Template)]
+#[template(path = "advisor_panel.html")]
+struct AdvisorPanelTemplate {
+    advisor: AdvisorPanel,
+}
+
+/// What the "Advisor suggests" panel shows
+struct AdvisorPanel {
+    selected: StrategyKind,
+    strategies: [StrategyKind; 3],
+    suggestions: Vec<Action>,
+    max_rounds: u16,
+}
+
+impl AdvisorPanel {
+    fn new(game: &GameState, selected: StrategyKind) -> Self {
+        Self {
+            selected,
+            strategies: StrategyKind::ALL,
+            suggestions: selected.strategy().plan(game),
+            max_rounds: MAX_AUTOPLAY_ROUNDS,
+        }
+    }
+
+    fn is_selected(&self, kind: &StrategyKind) -> bool {
+        self.selected == *kind
+    }
 }
 
 #[derive(Template)]
 #[template(path = "report.html")]
 struct ReportTemplate {
     state: GameState,
+    report: RoundReport,
+    flash: Vec<Flash>,
+}
+
+#[derive(Template)]
+#[template(path = "battle.html")]
+struct BattleTemplate {
+    user_name: String,
+    battle: BattleReport,
+}
+
+#[derive(Template)]
+#[template(path = "chronicle.html")]
+struct ChronicleTemplate {
+    user_name: String,
+    grade_title: &'static str,
+    chronicle: Vec<ChronicleEntry>,
 }
 
 #[derive(Deserialize)]
@@ -40,12 +103,52 @@ pub struct TradeForm {
     quantity: i32,
 }
 
-async fn game_view(State(game_state): State<SharedGameState>) -> impl IntoResponse {
-    let state = game_state.read().unwrap();
+#[derive(Deserialize)]
+pub struct RecruitForm {
+    quantity: i32,
+    #[serde(default)]
+    armored: bool,
+}
+
+#[derive(Deserialize)]
+pub struct RepairForm {
+    building: Building,
+}
+
+#[derive(Deserialize)]
+pub struct SaveForm {
+    slot: String,
+}
 
-    if let Some(ref game) = *state {
+#[derive(Deserialize)]
+pub struct AdvisorQuery {
+    strategy: StrategyKind,
+}
+
+#[derive(Deserialize)]
+pub struct AutoplayForm {
+    rounds: u16,
+}
+
+#[derive(Deserialize)]
+pub struct ChoiceForm {
+    choice: usize,
+}
+
+async fn game_view(Extension(session): Extension<SharedSession>) -> impl IntoResponse {
+    let mut session = session.write().unwrap();
+    let flash = session.take_flash();
+
+    if let Some(ref game) = session.game {
         let template = GameTemplate {
             state: game.clone(),
+            flash,
+            advisor: AdvisorPanel::new(game, session.advisor),
+            charts: charts::statistics(&game.history),
+            forecast: Forecast::of(game),
+            army: ArmyStrength::of(game, BattleKind::Raid),
+            outpost: battle::enemy_strength(game, BattleKind::Raid),
+            upkeep: Upkeep::owed(game.soldier_quantity),
         };
         Html(template.render().unwrap())
     } else {
@@ -54,245 +157,415 @@ async fn game_view(State(game_state): State<SharedGameState>) -> impl IntoRespon
 }
 
 async fn set_taxes(
-    State(game_state): State<SharedGameState>,
+    Extension(session): Extension<SharedSession>,
     Form(form): Form<TaxesForm>,
 ) -> impl IntoResponse {
-    let mut state = game_state.write().unwrap();
+    let mut session = session.write().unwrap();
 
-    if let Some(ref mut game) = *state {
+    if let Some(ref mut game) = session.game {
         game.taxes_level = form.taxes_level.min(5);
     }
 
-    drop(state);
+    drop(session);
     Redirect::to("/game")
 }
 
 async fn set_food_supply(
-    State(game_state): State<SharedGameState>,
+    Extension(session): Extension<SharedSession>,
     Form(form): Form<FoodSupplyForm>,
 ) -> impl IntoResponse {
-    let mut state = game_state.write().unwrap();
+    let mut session = session.write().unwrap();
 
-    if let Some(ref mut game) = *state {
+    if let Some(ref mut game) = session.game {
         game.food_supply = form.food_supply.min(5);
     }
 
-    drop(state);
+    drop(session);
+    Redirect::to("/game")
+}
+
+async fn build_farm(Extension(session): Extension<SharedSession>) -> impl IntoResponse {
+    let mut session = session.write().unwrap();
+
+    if let Some(ref mut game) = session.game {
+        let result = game.build_farm();
+        session.flash_result(result, "Built a new farm.".to_string());
+    }
+
+    drop(session);
+    Redirect::to("/game")
+}
+
+async fn build_mine(Extension(session): Extension<SharedSession>) -> impl IntoResponse {
+    let mut session = session.write().unwrap();
+
+    if let Some(ref mut game) = session.game {
+        let result = game.build_mine();
+        session.flash_result(result, "Built a new mine.".to_string());
+    }
+
+    drop(session);
+    Redirect::to("/game")
+}
+
+async fn build_smithy(Extension(session): Extension<SharedSession>) -> impl IntoResponse {
+    let mut session = session.write().unwrap();
+
+    if let Some(ref mut game) = session.game {
+        let result = game.build_smithy();
+        session.flash_result(result, "Built a new smithy.".to_string());
+    }
+
+    drop(session);
+    Redirect::to("/game")
+}
+
+async fn build_market(Extension(session): Extension<SharedSession>) -> impl IntoResponse {
+    let mut session = session.write().unwrap();
+
+    if let Some(ref mut game) = session.game {
+        let result = game.build_market();
+        session.flash_result(result, "Built a new market.".to_string());
+    }
+
+    drop(session);
     Redirect::to("/game")
 }
 
-async fn build_farm(State(game_state): State<SharedGameState>) -> impl IntoResponse {
-    let mut state = game_state.write().unwrap();
+async fn demolish_farm(Extension(session): Extension<SharedSession>) -> impl IntoResponse {
+    let mut session = session.write().unwrap();
 
-    if let Some(ref mut game) = *state {
-        let _ = game.build_farm();
+    if let Some(ref mut game) = session.game {
+        let result = game.demolish_farm();
+        session.flash_result(result, "Demolished a farm.".to_string());
     }
 
-    drop(state);
+    drop(session);
     Redirect::to("/game")
 }
 
-async fn build_mine(State(game_state): State<SharedGameState>) -> impl IntoResponse {
-    let mut state = game_state.write().unwrap();
+async fn demolish_mine(Extension(session): Extension<SharedSession>) -> impl IntoResponse {
+    let mut session = session.write().unwrap();
 
-    if let Some(ref mut game) = *state {
-        let _ = game.build_mine();
+    if let Some(ref mut game) = session.game {
+        let result = game.demolish_mine();
+        session.flash_result(result, "Demolished a mine.".to_string());
     }
 
-    drop(state);
+    drop(session);
     Redirect::to("/game")
 }
 
-async fn build_smithy(State(game_state): State<SharedGameState>) -> impl IntoResponse {
-    let mut state = game_state.write().unwrap();
+async fn demolish_smithy(Extension(session): Extension<SharedSession>) -> impl IntoResponse {
+    let mut session = session.write().unwrap();
 
-    if let Some(ref mut game) = *state {
-        let _ = game.build_smithy();
+    if let Some(ref mut game) = session.game {
+        let result = game.demolish_smithy();
+        session.flash_result(result, "Demolished a smithy.".to_string());
     }
 
-    drop(state);
+    drop(session);
     Redirect::to("/game")
 }
 
-async fn build_market(State(game_state): State<SharedGameState>) -> impl IntoResponse {
-    let mut state = game_state.write().unwrap();
+async fn demolish_market(Extension(session): Extension<SharedSession>) -> impl IntoResponse {
+    let mut session = session.write().unwrap();
 
-    if let Some(ref mut game) = *state {
-        let _ = game.build_market();
+    if let Some(ref mut game) = session.game {
+        let result = game.demolish_market();
+        session.flash_result(result, "Demolished a market.".to_string());
     }
 
-    drop(state);
+    drop(session);
     Redirect::to("/game")
 }
 
-async fn upgrade_castle(State(game_state): State<SharedGameState>) -> impl IntoResponse {
-    let mut state = game_state.write().unwrap();
+async fn repair(
+    Extension(session): Extension<SharedSession>,
+    Form(form): Form<RepairForm>,
+) -> impl IntoResponse {
+    let mut session = session.write().unwrap();
 
-    if let Some(ref mut game) = *state {
-        let _ = game.upgrade_castle();
+    if let Some(ref mut game) = session.game {
+        let result = game.repair(form.building);
+        session.flash_result(result, format!("Repaired the {}.", form.building.plural()));
     }
 
-    drop(state);
+    drop(session);
+    Redirect::to("/game")
+}
+
+async fn upgrade_castle(Extension(session): Extension<SharedSession>) -> impl IntoResponse {
+    let mut session = session.write().unwrap();
+
+    if let Some(ref mut game) = session.game {
+        let success = format!("Castle upgraded to level {}.", game.castle_level + 1);
+        let result = game.upgrade_castle();
+        session.flash_result(result, success);
+    }
+
+    drop(session);
     Redirect::to("/game")
 }
 
 async fn buy_food(
-    State(game_state): State<SharedGameState>,
+    Extension(session): Extension<SharedSession>,
     Form(form): Form<TradeForm>,
 ) -> impl IntoResponse {
-    let mut state = game_state.write().unwrap();
+    let mut session = session.write().unwrap();
 
-    if let Some(ref mut game) = *state {
-        let _ = game.buy_food(form.quantity);
+    if let Some(ref mut game) = session.game {
+        let result = game.buy_food(form.quantity);
+        session.flash_result(result, format!("Bought {} food.", form.quantity));
     }
 
-    drop(state);
+    drop(session);
     Redirect::to("/game")
 }
 
 async fn sell_food(
-    State(game_state): State<SharedGameState>,
+    Extension(session): Extension<SharedSession>,
     Form(form): Form<TradeForm>,
 ) -> impl IntoResponse {
-    let mut state = game_state.write().unwrap();
+    let mut session = session.write().unwrap();
 
-    if let Some(ref mut game) = *state {
-        let _ = game.sell_food(form.quantity);
+    if let Some(ref mut game) = session.game {
+        let result = game.sell_food(form.quantity);
+        session.flash_result(result, format!("Sold {} food.", form.quantity));
     }
 
-    drop(state);
+    drop(session);
     Redirect::to("/game")
 }
 
 async fn buy_iron(
-    State(game_state): State<SharedGameState>,
+    Extension(session): Extension<SharedSession>,
     Form(form): Form<TradeForm>,
 ) -> impl IntoResponse {
-    let mut state = game_state.write().unwrap();
+    let mut session = session.write().unwrap();
 
-    if let Some(ref mut game) = *state {
-        let _ = game.buy_iron(form.quantity as i16);
+    if let Some(ref mut game) = session.game {
+        let result = small_quantity(form.quantity).and_then(|quantity| game.buy_iron(quantity));
+        session.flash_result(result, format!("Bought {} iron.", form.quantity));
     }
 
-    drop(state);
+    drop(session);
     Redirect::to("/game")
 }
 
 async fn sell_iron(
-    State(game_state): State<SharedGameState>,
+    Extension(session): Extension<SharedSession>,
     Form(form): Form<TradeForm>,
 ) -> impl IntoResponse {
-    let mut state = game_state.write().unwrap();
+    let mut session = session.write().unwrap();
 
-    if let Some(ref mut game) = *state {
-        let _ = game.sell_iron(form.quantity as i16);
+    if let Some(ref mut game) = session.game {
+        let result = small_quantity(form.quantity).and_then(|quantity| game.sell_iron(quantity));
+        session.flash_result(result, format!("Sold {} iron.", form.quantity));
     }
 
-    drop(state);
+    drop(session);
     Redirect::to("/game")
 }
 
 async fn buy_weapons(
-    State(game_state): State<SharedGameState>,
+    Extension(session): Extension<SharedSession>,
     Form(form): Form<TradeForm>,
 ) -> impl IntoResponse {
-    let mut state = game_state.write().unwrap();
+    let mut session = session.write().unwrap();
 
-    if let Some(ref mut game) = *state {
-        let _ = game.buy_weapons(form.quantity as i16);
+    if let Some(ref mut game) = session.game {
+        let result = small_quantity(form.quantity).and_then(|quantity| game.buy_weapons(quantity));
+        session.flash_result(result, format!("Bought {} weapons.", form.quantity));
     }
 
-    drop(state);
+    drop(session);
     Redirect::to("/game")
 }
 
 async fn sell_weapons(
-    State(game_state): State<SharedGameState>,
+    Extension(session): Extension<SharedSession>,
     Form(form): Form<TradeForm>,
 ) -> impl IntoResponse {
-    let mut state = game_state.write().unwrap();
+    let mut session = session.write().unwrap();
 
-    if let Some(ref mut game) = *state {
-        let _ = game.sell_weapons(form.quantity as i16);
+    if let Some(ref mut game) = session.game {
+        let result = small_quantity(form.quantity).and_then(|quantity| game.sell_weapons(quantity));
+        session.flash_result(result, format!("Sold {} weapons.", form.quantity));
     }
 
-    drop(state);
+    drop(session);
     Redirect::to("/game")
 }
 
-async fn recruit_soldiers(
-    State(game_state): State<SharedGameState>,
+async fn buy_armor(
+    Extension(session): Extension<SharedSession>,
+    Form(form): Form<TradeForm>,
+) -> impl IntoResponse {
+    let mut session = session.write().unwrap();
+
+    if let Some(ref mut game) = session.game {
+        let result = small_quantity(form.quantity).and_then(|quantity| game.buy_armor(quantity));
+        session.flash_result(result, format!("Bought {} armor.", form.quantity));
+    }
+
+    drop(session);
+    Redirect::to("/game")
+}
+
+async fn sell_armor(
+    Extension(session): Extension<SharedSession>,
     Form(form): Form<TradeForm>,
 ) -> impl IntoResponse {
-    let mut state = game_state.write().unwrap();
+    let mut session = session.write().unwrap();
 
-    if let Some(ref mut game) = *state {
-        let _ = game.recruit_soldiers(form.quantity as i16);
+    if let Some(ref mut game) = session.game {
+        let result = small_quantity(form.quantity).and_then(|quantity| game.sell_armor(quantity));
+        session.flash_result(result, format!("Sold {} armor.", form.quantity));
     }
 
-    drop(state);
+    drop(session);
+    Redirect::to("/game")
+}
+
+async fn recruit_soldiers(
+    Extension(session): Extension<SharedSession>,
+    Form(form): Form<RecruitForm>,
+) -> impl IntoResponse {
+    let mut session = session.write().unwrap();
+
+    if let Some(ref mut game) = session.game {
+        let result = small_quantity(form.quantity)
+            .and_then(|quantity| game.recruit_soldiers(quantity, form.armored));
+        let success = if form.armored {
+            format!("Recruited {} armored soldiers.", form.quantity)
+        } else {
+            format!("Recruited {} soldiers.", form.quantity)
+        };
+        session.flash_result(result, success);
+    }
+
+    drop(session);
     Redirect::to("/game")
 }
 
 async fn discharge_soldiers(
-    State(game_state): State<SharedGameState>,
+    Extension(session): Extension<SharedSession>,
     Form(form): Form<TradeForm>,
 ) -> impl IntoResponse {
-    let mut state = game_state.write().unwrap();
+    let mut session = session.write().unwrap();
 
-    if let Some(ref mut game) = *state {
-        let _ = game.discharge_soldiers(form.quantity as i16);
+    if let Some(ref mut game) = session.game {
+        let result =
+            small_quantity(form.quantity).and_then(|quantity| game.discharge_soldiers(quantity));
+        session.flash_result(result, format!("Discharged {} soldiers.", form.quantity));
     }
 
-    drop(state);
+    drop(session);
     Redirect::to("/game")
 }
 
-async fn finish_round(State(game_state): State<SharedGameState>) -> impl IntoResponse {
-    let mut state = game_state.write().unwrap();
+async fn raid(Extension(session): Extension<SharedSession>) -> impl IntoResponse {
+    let mut session = session.write().unwrap();
+
+    if let Some(ref mut game) = session.game {
+        if let Err(err) = battle::raid(game) {
+            session.flash.push(Flash {
+                kind: FlashKind::Error,
+                message: err.to_string(),
+            });
+            drop(session);
+            return Redirect::to("/game");
+        }
+    }
 
-    if let Some(ref mut game) = *state {
-        // Store previous popularity for comparison
-        game.previous_popularity_percent = game.popularity_percent;
+    drop(session);
+    Redirect::to("/game/battle")
+}
 
-        // Apply round effects
-        apply_round_effects(game);
+async fn save_game(
+    State(app): State<AppState>,
+    Extension(session): Extension<SharedSession>,
+    Form(form): Form<SaveForm>,
+) -> impl IntoResponse {
+    let mut session = session.write().unwrap();
+    let owner = session.owner.clone();
+
+    if let Some(ref game) = session.game {
+        let slot = form.slot.trim();
+        let result = app
+            .saves
+            .for_owner(&owner)
+            .and_then(|saves| saves.save(slot, game));
+        if let Err(ref err) = result {
+            tracing::warn!("Failed to save game to slot '{}': {}", slot, err);
+        }
+        session.flash_result(result, format!("Game saved to slot '{}'.", slot));
+    }
 
-        // Generate random event (50% chance)
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
-        if rng.gen::<f32>() < 0.5 {
-            if let Some(event) = EventGenerator::generate_random_event(game) {
-                // Store event for display in report
-                game.last_event_title = Some(event.title.clone());
-                game.last_event_description = Some(event.description.clone());
-                event.apply_to_state(game);
-            }
-        } else {
-            game.last_event_title = None;
-            game.last_event_description = None;
+    drop(session);
+    Redirect::to("/game")
+}
+
+async fn finish_round(
+    State(app): State<AppState>,
+    Extension(session): Extension<SharedSession>,
+) -> impl IntoResponse {
+    let mut session = session.write().unwrap();
+    let owner = session.owner.clone();
+
+    if let Some(ref mut game) = session.game {
+        // A finished reign has no next round
+        if game.is_game_ended {
+            drop(session);
+            return Redirect::to("/game-over");
+        }
+
+        // The last round's event has to be answered first
+        if game.pending_event.is_some() {
+            session.flash.push(Flash {
+                kind: FlashKind::Error,
+                message: GameError::DecisionPending.to_string(),
+            });
+            drop(session);
+            return Redirect::to("/game/report");
         }
 
-        // Check for grade advancement
-        let _advanced = game.finish_round();
+        let report = round::play_seeded_round(game);
+
+        // Record a finished game before the autosave, so loading that save cannot
+        // record the same score again
+        app.highscores.record_finished(game);
+
+        // Autosave so a server restart does not lose the kingdom
+        if let Err(err) = app
+            .saves
+            .for_owner(&owner)
+            .and_then(|saves| saves.save(AUTOSAVE_SLOT, game))
+        {
+            tracing::warn!("Autosave to slot '{}' failed: {}", AUTOSAVE_SLOT, err);
+        }
 
         // Check for game over
-        if game.is_game_ended {
-            drop(state);
+        if report.game_ended {
+            drop(session);
             return Redirect::to("/game-over");
         }
     }
 
-    drop(state);
+    drop(session);
     Redirect::to("/game/report")
 }
 
-async fn report_view(State(game_state): State<SharedGameState>) -> impl IntoResponse {
-    let state = game_state.read().unwrap();
+async fn report_view(Extension(session): Extension<SharedSession>) -> impl IntoResponse {
+    let mut session = session.write().unwrap();
+    let flash = session.take_flash();
 
-    if let Some(ref game) = *state {
+    if let Some(ref game) = session.game {
         let template = ReportTemplate {
             state: game.clone(),
+            report: game.last_report.clone().unwrap_or_default(),
+            flash,
         };
         Html(template.render().unwrap())
     } else {
@@ -300,186 +573,187 @@ async fn report_view(State(game_state): State<SharedGameState>) -> impl IntoResp
     }
 }
 
-async fn continue_from_report(State(game_state): State<SharedGameState>) -> impl IntoResponse {
-    // Clear event data after viewing report
-    let mut state = game_state.write().unwrap();
-    if let Some(ref mut game) = *state {
-        game.last_event_title = None;
-        game.last_event_description = None;
+async fn battle_view(Extension(session): Extension<SharedSession>) -> impl IntoResponse {
+    let session = session.read().unwrap();
+
+    match session.game {
+        Some(ref game) if game.last_battle.is_some() => {
+            let template = BattleTemplate {
+                user_name: game.user_name.clone(),
+                battle: game.last_battle.clone().unwrap(),
+            };
+            Html(template.render().unwrap()).into_response()
+        }
+        Some(_) => Redirect::to("/game").into_response(),
+        None => {
+            Html("<h1>No active game. Please start a new game.</h1>".to_string()).into_response()
+        }
     }
-    drop(state);
-    Redirect::to("/game")
 }
 
-fn apply_round_effects(game: &mut GameState) {
-    use rand::Rng;
-    let mut rng = rand::thread_rng();
+async fn chronicle_view(Extension(session): Extension<SharedSession>) -> impl IntoResponse {
+    let session = session.read().unwrap();
 
-    // 1. Calculate taxes (with randomness)
-    if game.taxes_level != 0 && game.man_quantity > 0 {
-        let random_value =
-            rng.gen_range(-(game.man_quantity / 30 + 1)..=(game.man_quantity / 30 + 1));
-        game.taxes_value = game.man_quantity * game.taxes_level as i32 * 10 / 30 + random_value;
-        if game.taxes_value < 0 {
-            game.taxes_value = 0;
-        }
+    if let Some(ref game) = session.game {
+        let template = ChronicleTemplate {
+            user_name: game.user_name.clone(),
+            grade_title: game.get_grade_title(),
+            chronicle: game.chronicle.clone(),
+        };
+        Html(template.render().unwrap())
     } else {
-        game.taxes_value = 0;
+        Html("<h1>No active game. Please start a new game.</h1>".to_string())
     }
+}
 
-    // 2. Calculate market income (with randomness)
-    if game.market_quantity > 0 {
-        let random_value =
-            rng.gen_range(-(game.market_quantity as i32 + 1)..=(game.market_quantity as i32 + 1));
-        game.market_place_value = game.market_quantity as i32 * 200 + random_value;
+async fn advisor_panel(
+    Extension(session): Extension<SharedSession>,
+    Query(query): Query<AdvisorQuery>,
+) -> impl IntoResponse {
+    let mut session = session.write().unwrap();
+    session.advisor = query.strategy;
+
+    if let Some(ref game) = session.game {
+        let template = AdvisorPanelTemplate {
+            advisor: AdvisorPanel::new(game, query.strategy),
+        };
+        Html(template.render().unwrap())
     } else {
-        game.market_place_value = 0;
+        Html(String::new())
     }
+}
 
-    // 3. Calculate weapon production (happens BEFORE mine production)
-    if game.smithy_quantity > 0 {
-        let mut var3 = game.smithy_quantity as i32 * 8;
-        if var3 > game.iron_quantity as i32 {
-            var3 = game.iron_quantity as i32;
+async fn apply_advice(Extension(session): Extension<SharedSession>) -> impl IntoResponse {
+    let mut session = session.write().unwrap();
+    let strategy = session.advisor.strategy();
+
+    if let Some(ref mut game) = session.game {
+        let outcomes = advisor::play_turn(strategy, game);
+        if outcomes.is_empty() {
+            session.flash.push(Flash {
+                kind: FlashKind::Success,
+                message: "The advisor has nothing to suggest this round.".to_string(),
+            });
         }
-        if game.weapon_quantity as i32 + var3 > game.trade_limit as i32 {
-            var3 = game.trade_limit as i32 - game.weapon_quantity as i32;
+        for (action, result) in outcomes {
+            session.flash_result(result, format!("{}.", action));
         }
-        game.weapon_quantity += var3 as i16;
-        game.iron_quantity -= var3 as i16;
     }
 
-    // 4. Calculate mine production (happens AFTER weapon production)
-    if game.mine_quantity > 0 {
-        if game.iron_quantity as i32 + game.mine_quantity as i32 * 10 > game.trade_limit as i32 {
-            game.iron_quantity = game.trade_limit;
-        } else {
-            game.iron_quantity += game.mine_quantity * 10;
+    drop(session);
+    Redirect::to("/game")
+}
+
+async fn autoplay(
+    State(app): State<AppState>,
+    Extension(session): Extension<SharedSession>,
+    Form(form): Form<AutoplayForm>,
+) -> impl IntoResponse {
+    let mut session = session.write().unwrap();
+    let owner = session.owner.clone();
+    let kind = session.advisor;
+
+    if let Some(ref mut game) = session.game {
+        let start_year = game.year;
+        let start_grade = game.get_grade_title();
+        let reports = advisor::autoplay(kind.strategy(), game, form.rounds);
+
+        app.highscores.record_finished(game);
+        if let Err(err) = app
+            .saves
+            .for_owner(&owner)
+            .and_then(|saves| saves.save(AUTOSAVE_SLOT, game))
+        {
+            tracing::warn!("Autosave to slot '{}' failed: {}", AUTOSAVE_SLOT, err);
         }
-    }
 
-    // 5. Calculate harvest (with randomness)
-    if game.farm_quantity > 0 {
-        let random_value = rng.gen_range(0..64);
-        game.harvest_value =
-            game.farm_quantity as i32 * 116 + game.farm_quantity as i32 * random_value * 12;
-        game.harvest_percent = game.harvest_value / (game.farm_quantity as i32 * 5);
-    } else {
-        game.harvest_value = 0;
-        game.harvest_percent = 0;
+        let message = format!(
+            "{} played {} rounds ({} to {}): {} became {}, with {} gold and {} citizens.",
+            kind,
+            reports.len(),
+            start_year,
+            game.year,
+            start_grade,
+            game.get_grade_title(),
+            game.gold,
+            game.man_quantity
+        );
+        if game.is_game_ended {
+            drop(session);
+            return Redirect::to("/game-over");
+        }
+        session.flash.push(Flash {
+            kind: FlashKind::Success,
+            message,
+        });
     }
 
-    // 6. Calculate food consumption
-    let mut food_needed = game.man_quantity * (game.food_supply as i32 * 34 - 2) / 100;
-    if food_needed < 0 {
-        food_needed = 0;
-    }
+    drop(session);
+    Redirect::to("/game")
+}
 
-    let actual_food_level: i32;
-    if game.food_quantity >= food_needed {
-        game.food_quantity -= food_needed;
-        actual_food_level = game.food_supply as i32;
-    } else {
-        actual_food_level = game.food_quantity * 100 / game.man_quantity / 34;
-        game.food_quantity = 0;
-        game.change_popularity(-4);
-    }
-
-    // 7. Food supply effects on popularity and population
-    let (food_popularity_change, food_population_change) = match actual_food_level {
-        0 => (-5, -(game.man_quantity * 8 / 100)),
-        1 => (-2, -(game.man_quantity * 6 / 100)),
-        2 => (-1, -(game.man_quantity * 3 / 100)),
-        3 => (0, game.man_quantity * 1 / 100),
-        4 => (1, game.man_quantity * 4 / 100),
-        5 => (3, game.man_quantity * 7 / 100),
-        _ => (0, 0),
-    };
-
-    game.change_popularity(food_popularity_change);
-    game.change_population(food_population_change);
-
-    // 8. Add harvest to food
-    game.food_quantity += game.harvest_value;
-
-    // 9. Tax level effects on popularity and population (with randomness)
-    let random_base = rng.gen_range(0..4) + 4; // Generates 4-7
-    let (tax_popularity_change, tax_population_multiplier) = match game.taxes_level {
-        0 => (5, 10),
-        1 => (3, 8),
-        2 => (2, 4),
-        3 => (0, 1),
-        4 => (-3, -5),
-        5 => (-5, -9),
-        _ => (0, 1),
-    };
-
-    game.change_popularity(tax_popularity_change);
-    game.change_population(random_base * tax_population_multiplier);
-
-    // 10. Random population and popularity fluctuations
-    let random_population = rng.gen_range(-4..=4);
-    game.change_population(random_population);
-
-    let random_popularity = rng.gen_range(-4..=4);
-    game.change_popularity(random_popularity);
-
-    // 11. Add gold from taxes and markets
-    game.gold += game.taxes_value + game.market_place_value;
-
-    // 12. Adjust food prices based on harvest
-    if game.farm_quantity > 0 {
-        let mut price_for_food =
-            game.price_for_food_rate_constant as i32 * 100 / game.harvest_percent;
-        let random_value = rng.gen_range(0..(price_for_food / 5));
-
-        if price_for_food < 25 {
-            price_for_food = 25;
-        }
-        if price_for_food > 100 {
-            price_for_food = 100;
+async fn choose_event(
+    Extension(session): Extension<SharedSession>,
+    Form(form): Form<ChoiceForm>,
+) -> impl IntoResponse {
+    let mut session = session.write().unwrap();
+    if let Some(ref mut game) = session.game {
+        if let Err(err) = round::resolve_event(game, form.choice) {
+            session.flash.push(Flash {
+                kind: FlashKind::Error,
+                message: err.to_string(),
+            });
         }
-
-        price_for_food -= random_value;
-        game.price_for_food = price_for_food;
-    } else {
-        let random_value = rng.gen_range(
-            -(game.price_for_food_rate_constant as i32 * 50 / 100)
-                ..=(game.price_for_food_rate_constant as i32 * 50 / 100),
-        );
-        game.price_for_food = random_value + game.price_for_food_rate_constant as i32;
     }
+    drop(session);
+    Redirect::to("/game/report")
+}
 
-    // 13. Adjust armor prices (with randomness)
-    let var6 = game.price_for_armor_rate_constant as i32 * 20 / 100;
-    let random_value = rng.gen_range(-var6..=var6);
-    game.price_for_armor = game.price_for_armor_rate_constant as i32 + random_value;
-
-    // 14. Adjust weapon prices (with randomness)
-    let var6 = game.price_for_weapon_rate_constant as i32 * 20 / 100;
-    let random_value = rng.gen_range(-var6..=var6);
-    game.price_for_weapon = game.price_for_weapon_rate_constant as i32 + random_value;
+async fn continue_from_report(Extension(session): Extension<SharedSession>) -> impl IntoResponse {
+    // Clear event data after viewing report
+    let mut session = session.write().unwrap();
+    if let Some(ref mut game) = session.game {
+        game.last_event_title = None;
+        game.last_event_description = None;
+    }
+    drop(session);
+    Redirect::to("/game")
 }
 
-pub fn game_routes() -> Router<SharedGameState> {
+pub fn game_routes() -> Router<AppState> {
     Router::new()
         .route("/game", get(game_view))
         .route("/game/report", get(report_view))
+        .route("/game/chronicle", get(chronicle_view))
+        .route("/game/battle", get(battle_view))
         .route("/game/continue-from-report", post(continue_from_report))
+        .route("/game/event/choose", post(choose_event))
         .route("/game/set-taxes", post(set_taxes))
         .route("/game/set-food-supply", post(set_food_supply))
         .route("/game/build-farm", post(build_farm))
         .route("/game/build-mine", post(build_mine))
         .route("/game/build-smithy", post(build_smithy))
         .route("/game/build-market", post(build_market))
+        .route("/game/build/demolish-farm", post(demolish_farm))
+        .route("/game/build/demolish-mine", post(demolish_mine))
+        .route("/game/build/demolish-smithy", post(demolish_smithy))
+        .route("/game/build/demolish-market", post(demolish_market))
+        .route("/game/repair", post(repair))
         .route("/game/upgrade-castle", post(upgrade_castle))
         .route("/game/finish-round", post(finish_round))
+        .route("/game/save", post(save_game))
+        .route("/game/advisor", get(advisor_panel))
+        .route("/game/advisor/apply", post(apply_advice))
+        .route("/game/autoplay", post(autoplay))
         .route("/game/trade/buy-food", post(buy_food))
         .route("/game/trade/sell-food", post(sell_food))
         .route("/game/trade/buy-iron", post(buy_iron))
         .route("/game/trade/sell-iron", post(sell_iron))
         .route("/game/trade/buy-weapons", post(buy_weapons))
         .route("/game/trade/sell-weapons", post(sell_weapons))
+        .route("/game/trade/buy-armor", post(buy_armor))
+        .route("/game/trade/sell-armor", post(sell_armor))
         .route("/game/army/recruit", post(recruit_soldiers))
         .route("/game/army/discharge", post(discharge_soldiers))
+        .route("/game/army/raid", post(raid))
 }
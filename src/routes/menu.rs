@@ -1,14 +1,22 @@
 use askama::Template;
 use axum::{
-    extract::{Form, State},
-    response::{Html, IntoResponse, Redirect},
+    extract::{Form, Query, State},
+    http::header,
+    response::{Html, IntoResponse, Redirect, Response},
     routing::{get, post},
-    Router,
+    Extension, Router,
 };
 use serde::Deserialize;
 
-use crate::game::{Difficulty, GameState, Gender};
-use crate::routes::game::SharedGameState;
+use crate::app_state::AppState;
+use crate::game::chronicle::ChronicleEntry;
+use crate::game::error::GameError;
+use crate::game::{rng, CustomRules, Difficulty, GameState, Gender};
+use crate::highscores::{HighscoreEntry, HighscorePage};
+use crate::reign_report::ReignReport;
+use crate::saves::SaveSummary;
+use crate::session::SharedSession;
+use rust_emperor::filters;
 
 #[derive(Template)]
 #[template(path = "menu.html")]
@@ -17,11 +25,20 @@ struct MenuTemplate {
     won: bool,
     score: i32,
     player_name: String,
+    // Summary of the custom rules the finished game was played with
+    rules: Option<String>,
+    chronicle: Vec<ChronicleEntry>,
 }
 
 #[derive(Template)]
 #[template(path = "new_game_form.html")]
-struct NewGameFormTemplate;
+struct NewGameFormTemplate {
+    player_name: String,
+    difficulty: Difficulty,
+    seed: String,
+    custom: CustomRulesForm,
+    error: Option<String>,
+}
 
 #[derive(Template)]
 #[template(path = "about.html")]
@@ -30,7 +47,38 @@ struct AboutTemplate;
 #[derive(Template)]
 #[template(path = "highscores.html")]
 struct HighscoresTemplate {
-    scores: Vec<(String, i32, String)>, // (name, score, difficulty)
+    page: HighscorePage,
+    difficulties: [Difficulty; 4],
+    difficulty: Option<Difficulty>,
+    player: Option<String>,
+    personal_bests: Vec<HighscoreEntry>,
+    // Current filters as extra query parameters for the pager links
+    filter_query: String,
+}
+
+impl HighscoresTemplate {
+    fn is_selected(&self, difficulty: &Difficulty) -> bool {
+        self.difficulty == Some(*difficulty)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct HighscoresQuery {
+    difficulty: Option<Difficulty>,
+    player: Option<String>,
+    page: Option<usize>,
+}
+
+#[derive(Template)]
+#[template(path = "saves.html")]
+struct SavesTemplate {
+    saves: Vec<SaveSummary>,
+    message: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct SlotForm {
+    slot: String,
 }
 
 #[derive(Deserialize)]
@@ -38,6 +86,79 @@ pub struct NewGameForm {
     player_name: String,
     gender: String,
     difficulty: String,
+    #[serde(default)]
+    seed: String,
+    #[serde(flatten)]
+    custom: CustomRulesForm,
+}
+
+/// The custom-difficulty fields of the new-game form, kept as typed so a rejected
+/// form can be shown again unchanged
+#[derive(Deserialize, Default)]
+pub struct CustomRulesForm {
+    #[serde(default)]
+    custom_gold: String,
+    #[serde(default)]
+    custom_food: String,
+    #[serde(default)]
+    custom_citizens: String,
+    #[serde(default)]
+    custom_farms: String,
+    #[serde(default)]
+    custom_start_year: String,
+    #[serde(default)]
+    custom_end_year: String,
+    #[serde(default)]
+    custom_event_percent: String,
+    #[serde(default)]
+    custom_trade_limit: String,
+    // Checkboxes are only sent when checked
+    custom_year_limit: Option<String>,
+}
+
+impl From<CustomRules> for CustomRulesForm {
+    fn from(rules: CustomRules) -> Self {
+        Self {
+            custom_gold: rules.gold.to_string(),
+            custom_food: rules.food.to_string(),
+            custom_citizens: rules.citizens.to_string(),
+            custom_farms: rules.farms.to_string(),
+            custom_start_year: rules.start_year.to_string(),
+            custom_end_year: rules.end_year.to_string(),
+            custom_event_percent: rules.event_percent.to_string(),
+            custom_trade_limit: rules.trade_limit.to_string(),
+            custom_year_limit: rules.year_limit.then(|| "on".to_string()),
+        }
+    }
+}
+
+impl CustomRulesForm {
+    fn to_rules(&self) -> Result<CustomRules, GameError> {
+        fn parse<T: std::str::FromStr>(value: &str, rule: &'static str) -> Result<T, GameError> {
+            value.trim().parse().map_err(|_| GameError::InvalidRule {
+                rule,
+                reason: "must be a number in range",
+            })
+        }
+
+        let rules = CustomRules {
+            gold: parse(&self.custom_gold, "Starting gold")?,
+            food: parse(&self.custom_food, "Starting food")?,
+            citizens: parse(&self.custom_citizens, "Starting citizens")?,
+            farms: parse(&self.custom_farms, "Starting farms")?,
+            start_year: parse(&self.custom_start_year, "The start year")?,
+            end_year: parse(&self.custom_end_year, "The end year")?,
+            event_percent: parse(&self.custom_event_percent, "Event frequency")?,
+            trade_limit: parse(&self.custom_trade_limit, "The trade limit")?,
+            year_limit: self.custom_year_limit.is_some(),
+        };
+        rules.validate()?;
+        Ok(rules)
+    }
+
+    fn year_limit(&self) -> bool {
+        self.custom_year_limit.is_some()
+    }
 }
 
 async fn index() -> impl IntoResponse {
@@ -46,38 +167,108 @@ async fn index() -> impl IntoResponse {
         won: false,
         score: 0,
         player_name: String::new(),
+        rules: None,
+        chronicle: Vec::new(),
     };
     Html(template.render().unwrap())
 }
 
-async fn game_over(State(game_state): State<SharedGameState>) -> impl IntoResponse {
-    let state = game_state.read().unwrap();
+async fn game_over(
+    State(app): State<AppState>,
+    Extension(session): Extension<SharedSession>,
+) -> impl IntoResponse {
+    let mut session = session.write().unwrap();
+
+    let (won, score, player_name, rules, chronicle) = if let Some(ref mut game) = session.game {
+        // Record each finished game exactly once, even if this page is reloaded
+        app.highscores.record_finished(game);
 
-    let (won, score, player_name) = if let Some(ref game) = *state {
         let score = game.calculate_score();
-        (game.is_won, score, game.user_name.clone())
+        let rules = game.custom_rules.map(|rules| rules.summary());
+        (
+            game.is_won,
+            score,
+            game.user_name.clone(),
+            rules,
+            game.chronicle.clone(),
+        )
     } else {
-        (false, 0, String::new())
+        (false, 0, String::new(), None, Vec::new())
     };
 
-    drop(state);
+    drop(session);
 
     let template = MenuTemplate {
         show_game_over: true,
         won,
         score,
         player_name,
+        rules,
+        chronicle,
     };
     Html(template.render().unwrap())
 }
 
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Md,
+    Html,
+}
+
+#[derive(Deserialize)]
+pub struct ReportQuery {
+    format: ReportFormat,
+}
+
+/// Downloads the finished reign as a standalone Markdown or HTML file
+async fn reign_report(
+    Extension(session): Extension<SharedSession>,
+    Query(query): Query<ReportQuery>,
+) -> Response {
+    let session = session.read().unwrap();
+    let Some(game) = session.game.as_ref().filter(|game| game.is_game_ended) else {
+        return Redirect::to("/").into_response();
+    };
+    let report = ReignReport::from_game(game);
+    drop(session);
+
+    let (content_type, file_name, body) = match query.format {
+        ReportFormat::Md => (
+            "text/markdown; charset=utf-8",
+            report.file_name("md"),
+            report.markdown(),
+        ),
+        ReportFormat::Html => (
+            "text/html; charset=utf-8",
+            report.file_name("html"),
+            report.html(),
+        ),
+    };
+    let disposition = format!("attachment; filename=\"{}\"", file_name);
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+        .into_response()
+}
+
 async fn new_game_form() -> impl IntoResponse {
-    let template = NewGameFormTemplate;
+    let template = NewGameFormTemplate {
+        player_name: String::new(),
+        difficulty: Difficulty::Easy,
+        seed: rng::random_seed().to_string(),
+        custom: CustomRules::default().into(),
+        error: None,
+    };
     Html(template.render().unwrap())
 }
 
 async fn start_game(
-    State(game_state): State<SharedGameState>,
+    Extension(session): Extension<SharedSession>,
     Form(form): Form<NewGameForm>,
 ) -> impl IntoResponse {
     let gender = match form.gender.as_str() {
@@ -89,18 +280,34 @@ async fn start_game(
         "Easy" => Difficulty::Easy,
         "Medium" => Difficulty::Medium,
         "Hard" => Difficulty::Hard,
+        "Custom" => Difficulty::Custom,
         _ => Difficulty::Easy,
     };
 
-    let state = GameState::new(form.player_name, gender, difficulty);
+    let seed = rng::parse_seed(&form.seed).unwrap_or_else(rng::random_seed);
+    let state = if difficulty == Difficulty::Custom {
+        match form.custom.to_rules() {
+            Ok(rules) => GameState::with_rules(form.player_name, gender, rules, seed),
+            Err(err) => {
+                // Show the form again with what the player typed
+                let template = NewGameFormTemplate {
+                    player_name: form.player_name,
+                    difficulty,
+                    seed: form.seed,
+                    custom: form.custom,
+                    error: Some(err.to_string()),
+                };
+                return Html(template.render().unwrap()).into_response();
+            }
+        }
+    } else {
+        GameState::with_seed(form.player_name, gender, difficulty, seed)
+    };
 
-    // Store the game state
-    {
-        let mut game = game_state.write().unwrap();
-        *game = Some(state);
-    }
+    // Store the game in the caller's session
+    session.write().unwrap().game = Some(state);
 
-    Redirect::to("/game")
+    Redirect::to("/game").into_response()
 }
 
 async fn about() -> impl IntoResponse {
@@ -108,21 +315,111 @@ async fn about() -> impl IntoResponse {
     Html(template.render().unwrap())
 }
 
-async fn highscores() -> impl IntoResponse {
-    // Placeholder scores - in a real app, load from database/file
-    let scores = vec![
-        ("Emperor_Magnus".to_string(), 15000, "Hard".to_string()),
-        ("Queen_Victoria".to_string(), 12500, "Medium".to_string()),
-        ("King_Arthur".to_string(), 10000, "Medium".to_string()),
-        ("Duke_William".to_string(), 8500, "Easy".to_string()),
-        ("Baroness_Jane".to_string(), 5000, "Easy".to_string()),
-    ];
+async fn highscores(
+    State(app): State<AppState>,
+    Query(query): Query<HighscoresQuery>,
+) -> impl IntoResponse {
+    let player = query
+        .player
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty());
+
+    let page = app
+        .highscores
+        .page(query.difficulty, player.as_deref(), query.page.unwrap_or(1));
+    let personal_bests = player
+        .as_deref()
+        .map(|name| app.highscores.personal_bests(name))
+        .unwrap_or_default();
+
+    let mut filter_query = String::new();
+    if let Some(difficulty) = query.difficulty {
+        filter_query.push_str(&format!("&difficulty={}", difficulty));
+    }
+    if let Some(ref name) = player {
+        filter_query.push_str(&format!(
+            "&player={}",
+            askama::filters::urlencode(name).unwrap_or_default()
+        ));
+    }
 
-    let template = HighscoresTemplate { scores };
+    let template = HighscoresTemplate {
+        page,
+        difficulties: Difficulty::ALL,
+        difficulty: query.difficulty,
+        player,
+        personal_bests,
+        filter_query,
+    };
+    Html(template.render().unwrap())
+}
+
+/// The slots of the session's owner; none if the owner is unusable
+fn owned_saves(app: &AppState, owner: &str) -> Vec<SaveSummary> {
+    app.saves
+        .for_owner(owner)
+        .map(|saves| saves.list())
+        .unwrap_or_default()
+}
+
+async fn saves_view(
+    State(app): State<AppState>,
+    Extension(session): Extension<SharedSession>,
+) -> impl IntoResponse {
+    let owner = session.read().unwrap().owner.clone();
+    let template = SavesTemplate {
+        saves: owned_saves(&app, &owner),
+        message: None,
+    };
+    Html(template.render().unwrap())
+}
+
+async fn load_save(
+    State(app): State<AppState>,
+    Extension(session): Extension<SharedSession>,
+    Form(form): Form<SlotForm>,
+) -> impl IntoResponse {
+    let owner = session.read().unwrap().owner.clone();
+    match app
+        .saves
+        .for_owner(&owner)
+        .and_then(|saves| saves.load(&form.slot))
+    {
+        Ok(state) => {
+            session.write().unwrap().game = Some(state);
+            Redirect::to("/game").into_response()
+        }
+        Err(err) => {
+            let template = SavesTemplate {
+                saves: owned_saves(&app, &owner),
+                message: Some(format!("Could not load '{}': {}", form.slot, err)),
+            };
+            Html(template.render().unwrap()).into_response()
+        }
+    }
+}
+
+async fn delete_save(
+    State(app): State<AppState>,
+    Extension(session): Extension<SharedSession>,
+    Form(form): Form<SlotForm>,
+) -> impl IntoResponse {
+    let owner = session.read().unwrap().owner.clone();
+    let message = app
+        .saves
+        .for_owner(&owner)
+        .and_then(|saves| saves.delete(&form.slot))
+        .err()
+        .map(|err| format!("Could not delete '{}': {}", form.slot, err));
+
+    let template = SavesTemplate {
+        saves: owned_saves(&app, &owner),
+        message,
+    };
     Html(template.render().unwrap())
 }
 
-pub fn menu_routes() -> Router<SharedGameState> {
+pub fn menu_routes() -> Router<AppState> {
     Router::new()
         .route("/", get(index))
         .route("/new-game-form", get(new_game_form))
@@ -130,4 +427,8 @@ pub fn menu_routes() -> Router<SharedGameState> {
         .route("/about", get(about))
         .route("/highscores", get(highscores))
         .route("/game-over", get(game_over))
+        .route("/game-over/report", get(reign_report))
+        .route("/saves", get(saves_view))
+        .route("/saves/load", post(load_save))
+        .route("/saves/delete", post(delete_save))
 }
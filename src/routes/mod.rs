@@ -1,5 +1,7 @@
+pub mod api;
 pub mod game;
 pub mod menu;
 
+pub use api::api_routes;
 pub use game::game_routes;
 pub use menu::menu_routes;
@@ -0,0 +1,403 @@
+//! Persistent save slots
+//! Each slot is a JSON file holding a versioned copy of a `GameState`. Every owner (one
+//! browser) has a directory of its own inside the save directory and sees only its slots.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::game::GameState;
+
+/// Bump this whenever a change to `GameState` needs a migration step in `migrate`
+pub const SAVE_FORMAT_VERSION: u32 = 2;
+
+const MAX_SLOT_NAME_LEN: usize = 32;
+
+/// Slot used for the automatic save at the end of every round
+pub const AUTOSAVE_SLOT: &str = "autosave";
+
+#[derive(Debug)]
+pub enum SaveError {
+    InvalidSlotName,
+    /// The owner id has nothing usable as a directory name
+    InvalidOwner,
+    NotFound,
+    UnsupportedVersion(u32),
+    Io(io::Error),
+    Format(serde_json::Error),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::InvalidSlotName => write!(
+                f,
+                "Slot names may only use letters, digits, spaces, '-' and '_' (max {} characters)",
+                MAX_SLOT_NAME_LEN
+            ),
+            SaveError::InvalidOwner => write!(f, "These save slots have no valid owner"),
+            SaveError::NotFound => write!(f, "Save slot not found"),
+            SaveError::UnsupportedVersion(version) => write!(
+                f,
+                "Save format version {} is newer than this game supports ({})",
+                version, SAVE_FORMAT_VERSION
+            ),
+            SaveError::Io(err) => write!(f, "Could not access save file: {}", err),
+            SaveError::Format(err) => write!(f, "Save file is corrupted: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<io::Error> for SaveError {
+    fn from(err: io::Error) -> Self {
+        if err.kind() == io::ErrorKind::NotFound {
+            SaveError::NotFound
+        } else {
+            SaveError::Io(err)
+        }
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(err: serde_json::Error) -> Self {
+        SaveError::Format(err)
+    }
+}
+
+/// On-disk layout of a save slot
+#[derive(Debug, Serialize, Deserialize)]
+struct SaveFile {
+    version: u32,
+    saved_at: u64,
+    state: Value,
+}
+
+/// What the load screen shows for each slot without deserializing the whole game
+#[derive(Debug, Clone)]
+pub struct SaveSummary {
+    pub slot: String,
+    pub player_name: String,
+    pub grade_title: String,
+    pub year: u16,
+    pub saved_at: u64,
+}
+
+/// Directory-backed store of named save slots
+#[derive(Debug, Clone)]
+pub struct SaveStore {
+    dir: PathBuf,
+}
+
+impl SaveStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The slots of one owner, in a directory of their own. Owner ids come from a signed
+    /// cookie; anything but letters and digits is dropped all the same, and an owner with
+    /// nothing left never gets the shared save directory.
+    pub fn for_owner(&self, owner: &str) -> Result<Self, SaveError> {
+        let owner: String = owner
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect();
+        if owner.is_empty() {
+            return Err(SaveError::InvalidOwner);
+        }
+        Ok(Self::new(self.dir.join(owner)))
+    }
+
+    pub fn save(&self, slot: &str, state: &GameState) -> Result<(), SaveError> {
+        let path = self.slot_path(slot)?;
+        fs::create_dir_all(&self.dir)?;
+
+        let file = SaveFile {
+            version: SAVE_FORMAT_VERSION,
+            saved_at: unix_now(),
+            state: serde_json::to_value(state)?,
+        };
+
+        // Write to a temporary file first so a crash never leaves a half-written save
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_vec_pretty(&file)?)?;
+        fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+
+    pub fn load(&self, slot: &str) -> Result<GameState, SaveError> {
+        let file = self.read_file(slot)?;
+        let state = migrate(file.state, file.version)?;
+        Ok(serde_json::from_value(state)?)
+    }
+
+    pub fn delete(&self, slot: &str) -> Result<(), SaveError> {
+        fs::remove_file(self.slot_path(slot)?)?;
+        Ok(())
+    }
+
+    /// Lists every readable slot, most recently saved first
+    pub fn list(&self) -> Vec<SaveSummary> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+
+        let mut summaries: Vec<SaveSummary> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let path = entry.path();
+                if path.extension()? != "json" {
+                    return None;
+                }
+                let slot = path.file_stem()?.to_str()?.to_string();
+                let file = self.read_file(&slot).ok()?;
+                let state: GameState =
+                    serde_json::from_value(migrate(file.state, file.version).ok()?).ok()?;
+                Some(SaveSummary {
+                    slot,
+                    grade_title: state.get_grade_title().to_string(),
+                    player_name: state.user_name,
+                    year: state.year,
+                    saved_at: file.saved_at,
+                })
+            })
+            .collect();
+
+        summaries.sort_by_key(|summary| std::cmp::Reverse(summary.saved_at));
+        summaries
+    }
+
+    fn read_file(&self, slot: &str) -> Result<SaveFile, SaveError> {
+        let data = fs::read(self.slot_path(slot)?)?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    fn slot_path(&self, slot: &str) -> Result<PathBuf, SaveError> {
+        if !is_valid_slot_name(slot) {
+            return Err(SaveError::InvalidSlotName);
+        }
+        Ok(self.dir.join(format!("{}.json", slot)))
+    }
+}
+
+pub fn is_valid_slot_name(slot: &str) -> bool {
+    !slot.trim().is_empty()
+        && slot.len() <= MAX_SLOT_NAME_LEN
+        && slot
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == ' ' || c == '-' || c == '_')
+}
+
+/// Upgrades a saved state from `version` to the current format.
+/// Fields added to `GameState` without a migration step fall back to their defaults.
+fn migrate(mut state: Value, version: u32) -> Result<Value, SaveError> {
+    if version > SAVE_FORMAT_VERSION {
+        return Err(SaveError::UnsupportedVersion(version));
+    }
+
+    if version < 2 {
+        // Version 1 called the iron price the armor price; armor itself is new
+        rename(&mut state, "price_for_armor", "price_for_iron");
+        rename(
+            &mut state,
+            "price_for_armor_rate_constant",
+            "price_for_iron_rate_constant",
+        );
+        if let Some(report) = state.get_mut("last_report") {
+            for snapshot in ["start", "end"] {
+                if let Some(snapshot) = report.get_mut(snapshot) {
+                    rename(snapshot, "price_for_armor", "price_for_iron");
+                }
+            }
+            if let Some(Value::Array(steps)) = report.get_mut("steps") {
+                for step in steps {
+                    for snapshot in ["before", "after"] {
+                        if let Some(snapshot) = step.get_mut(snapshot) {
+                            rename(snapshot, "price_for_armor", "price_for_iron");
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(state)
+}
+
+/// Moves the value of `from` in a JSON object to `to`
+fn rename(object: &mut Value, from: &str, to: &str) {
+    if let Some(object) = object.as_object_mut() {
+        if let Some(value) = object.remove(from) {
+            object.insert(to.to_string(), value);
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Difficulty, Gender};
+
+    fn temp_store(name: &str) -> SaveStore {
+        let dir = std::env::temp_dir().join(format!(
+            "rust-emperor-saves-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        SaveStore::new(dir)
+    }
+
+    #[test]
+    fn test_save_load_delete_roundtrip() {
+        let store = temp_store("roundtrip");
+        let mut state = GameState::new("Arthur".to_string(), Gender::Male, Difficulty::Hard);
+        state.gold = 1234;
+
+        store.save("slot one", &state).unwrap();
+        let loaded = store.load("slot one").unwrap();
+        assert_eq!(loaded.gold, 1234);
+        assert_eq!(loaded.user_name, "Arthur");
+
+        let list = store.list();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].slot, "slot one");
+
+        store.delete("slot one").unwrap();
+        assert!(matches!(store.load("slot one"), Err(SaveError::NotFound)));
+    }
+
+    #[test]
+    fn test_rejects_path_traversal() {
+        let store = temp_store("traversal");
+        let state = GameState::default();
+        assert!(matches!(
+            store.save("../evil", &state),
+            Err(SaveError::InvalidSlotName)
+        ));
+    }
+
+    #[test]
+    fn test_loads_save_with_missing_fields() {
+        let store = temp_store("missing-fields");
+        let mut state = serde_json::to_value(GameState::default()).unwrap();
+        state.as_object_mut().unwrap().remove("trade_limit");
+        let file = SaveFile {
+            version: 1,
+            saved_at: 0,
+            state,
+        };
+        fs::create_dir_all(&store.dir).unwrap();
+        fs::write(
+            store.dir.join("old.json"),
+            serde_json::to_vec(&file).unwrap(),
+        )
+        .unwrap();
+
+        let loaded = store.load("old").unwrap();
+        assert_eq!(loaded.trade_limit, GameState::default().trade_limit);
+    }
+
+    #[test]
+    fn test_loads_version_1_save_without_history_or_chronicle() {
+        let store = temp_store("no-history");
+        let mut state = serde_json::to_value(GameState::default()).unwrap();
+        let object = state.as_object_mut().unwrap();
+        object.remove("history");
+        object.remove("chronicle");
+        let file = SaveFile {
+            version: 1,
+            saved_at: 0,
+            state,
+        };
+        fs::create_dir_all(&store.dir).unwrap();
+        fs::write(
+            store.dir.join("old.json"),
+            serde_json::to_vec(&file).unwrap(),
+        )
+        .unwrap();
+
+        let loaded = store.load("old").unwrap();
+        assert!(loaded.history.is_empty());
+        assert!(loaded.chronicle.is_empty());
+        assert_eq!(loaded.gold, GameState::default().gold);
+    }
+
+    #[test]
+    fn test_migrates_iron_price_from_version_1() {
+        let store = temp_store("version-1");
+        let mut state = serde_json::to_value(GameState::default()).unwrap();
+        let object = state.as_object_mut().unwrap();
+        for field in [
+            "price_for_iron",
+            "price_for_iron_rate_constant",
+            "armor_quantity",
+            "armored_soldier_quantity",
+        ] {
+            object.remove(field);
+        }
+        object.insert("price_for_armor".to_string(), 55.into());
+        object.insert("price_for_armor_rate_constant".to_string(), 60.into());
+        let file = SaveFile {
+            version: 1,
+            saved_at: 0,
+            state,
+        };
+        fs::create_dir_all(&store.dir).unwrap();
+        fs::write(
+            store.dir.join("v1.json"),
+            serde_json::to_vec(&file).unwrap(),
+        )
+        .unwrap();
+
+        let loaded = store.load("v1").unwrap();
+        let fresh = GameState::default();
+        assert_eq!(loaded.price_for_iron, 55);
+        assert_eq!(loaded.price_for_iron_rate_constant, 60);
+        assert_eq!(loaded.price_for_armor, fresh.price_for_armor);
+        assert_eq!(
+            loaded.price_for_armor_rate_constant,
+            fresh.price_for_armor_rate_constant
+        );
+        assert_eq!(loaded.armor_quantity, 0);
+    }
+
+    #[test]
+    fn test_owners_see_only_their_slots() {
+        let store = temp_store("owners");
+        let alice = store.for_owner("a1").unwrap();
+        let bob = store.for_owner("b2").unwrap();
+        assert!(is_valid_slot_name(AUTOSAVE_SLOT));
+
+        let mut state = GameState::new("Jane".to_string(), Gender::Female, Difficulty::Easy);
+        state.gold = 111;
+        alice.save(AUTOSAVE_SLOT, &state).unwrap();
+        state.gold = 222;
+        bob.save(AUTOSAVE_SLOT, &state).unwrap();
+
+        assert_eq!(alice.load(AUTOSAVE_SLOT).unwrap().gold, 111);
+        assert_eq!(bob.load(AUTOSAVE_SLOT).unwrap().gold, 222);
+        assert_eq!(alice.list().len(), 1);
+        assert!(store.list().is_empty());
+
+        bob.delete(AUTOSAVE_SLOT).unwrap();
+        assert!(alice.load(AUTOSAVE_SLOT).is_ok());
+        assert_eq!(store.for_owner("../a1").unwrap().dir, alice.dir);
+        assert!(matches!(
+            store.for_owner("../"),
+            Err(SaveError::InvalidOwner)
+        ));
+    }
+}
@@ -0,0 +1,304 @@
+//! Cookie-based player sessions
+//! Every browser gets its own signed session id, and each session holds its own game.
+//! A second, long-lived owner id marks the browser's save slots, so they outlive the
+//! session.
+
+use axum::{
+    extract::{FromRef, Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_extra::extract::cookie::{Cookie, Key, SameSite, SignedCookieJar};
+use rand::RngCore;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::game::advisor::StrategyKind;
+use crate::game::GameState;
+
+/// Name of the cookie carrying the signed session id
+pub const SESSION_COOKIE: &str = "emperor_session";
+
+/// Name of the cookie carrying the signed id of the save slots' owner
+pub const OWNER_COOKIE: &str = "emperor_owner";
+
+/// Sessions that have not been used for this long are dropped
+pub const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(2 * 60 * 60);
+
+/// How often the background task looks for idle sessions
+pub const SESSION_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Everything the server keeps for one player
+#[derive(Debug, Default)]
+pub struct Session {
+    /// Whose save slots this session uses
+    pub owner: String,
+    pub game: Option<GameState>,
+    /// Messages shown once, on the next render of the game screen
+    pub flash: Vec<Flash>,
+    /// Strategy shown in the advisor panel
+    pub advisor: StrategyKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlashKind {
+    Success,
+    Error,
+}
+
+/// Feedback about the last action the player took
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Flash {
+    pub kind: FlashKind,
+    pub message: String,
+}
+
+impl Flash {
+    /// CSS modifier for the message box
+    pub fn css_class(&self) -> &'static str {
+        match self.kind {
+            FlashKind::Success => "flash-success",
+            FlashKind::Error => "flash-error",
+        }
+    }
+}
+
+impl Session {
+    /// Queues `success` if the action worked, or the error message if it failed
+    pub fn flash_result<E: fmt::Display>(&mut self, result: Result<(), E>, success: String) {
+        let flash = match result {
+            Ok(()) => Flash {
+                kind: FlashKind::Success,
+                message: success,
+            },
+            Err(err) => Flash {
+                kind: FlashKind::Error,
+                message: err.to_string(),
+            },
+        };
+        self.flash.push(flash);
+    }
+
+    /// Removes and returns every queued message
+    pub fn take_flash(&mut self) -> Vec<Flash> {
+        std::mem::take(&mut self.flash)
+    }
+}
+
+pub type SharedSession = Arc<RwLock<Session>>;
+
+struct SessionEntry {
+    session: SharedSession,
+    last_seen: Instant,
+}
+
+/// In-memory session store keyed by session id
+#[derive(Clone, Default)]
+pub struct SessionStore {
+    sessions: Arc<RwLock<HashMap<String, SessionEntry>>>,
+}
+
+impl SessionStore {
+    /// Looks up the session for `id`, creating a fresh one if the id is unknown or expired.
+    /// Returns the id actually in use and whether it was newly created.
+    pub fn get_or_create(&self, id: Option<&str>) -> (String, SharedSession, bool) {
+        self.get_or_create_within(id, SESSION_IDLE_TIMEOUT)
+    }
+
+    /// `get_or_create` with sessions expiring after `max_idle`, even before the next sweep
+    fn get_or_create_within(
+        &self,
+        id: Option<&str>,
+        max_idle: Duration,
+    ) -> (String, SharedSession, bool) {
+        let mut sessions = self.sessions.write().unwrap();
+        let now = Instant::now();
+
+        if let Some(id) = id {
+            match sessions.get_mut(id) {
+                Some(entry) if entry.last_seen.elapsed() <= max_idle => {
+                    entry.last_seen = now;
+                    return (id.to_string(), entry.session.clone(), false);
+                }
+                Some(_) => {
+                    sessions.remove(id);
+                }
+                None => {}
+            }
+        }
+
+        let id = generate_id();
+        let session = SharedSession::default();
+        sessions.insert(
+            id.clone(),
+            SessionEntry {
+                session: session.clone(),
+                last_seen: now,
+            },
+        );
+        (id, session, true)
+    }
+
+    /// Drops every session idle for longer than `max_idle`, returning how many were removed
+    pub fn remove_idle(&self, max_idle: Duration) -> usize {
+        let mut sessions = self.sessions.write().unwrap();
+        let before = sessions.len();
+        sessions.retain(|_, entry| entry.last_seen.elapsed() <= max_idle);
+        before - sessions.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn generate_id() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Periodically removes idle sessions; runs until the server stops
+pub async fn expire_idle_sessions(store: SessionStore) {
+    let mut interval = tokio::time::interval(SESSION_SWEEP_INTERVAL);
+    loop {
+        interval.tick().await;
+        let removed = store.remove_idle(SESSION_IDLE_TIMEOUT);
+        if removed > 0 {
+            tracing::debug!(
+                "Expired {} idle session(s), {} active",
+                removed,
+                store.len()
+            );
+        }
+    }
+}
+
+/// Middleware that resolves the caller's session from the signed cookie and
+/// makes it available to handlers as `Extension<SharedSession>`
+pub async fn session_layer<S>(
+    State(state): State<S>,
+    jar: SignedCookieJar,
+    mut request: Request,
+    next: Next,
+) -> Response
+where
+    S: Clone + Send + Sync + 'static,
+    SessionStore: FromRef<S>,
+    Key: FromRef<S>,
+{
+    let store = SessionStore::from_ref(&state);
+    let cookie_id = jar
+        .get(SESSION_COOKIE)
+        .map(|cookie| cookie.value().to_string());
+    let (id, session, created) = store.get_or_create(cookie_id.as_deref());
+
+    // A new session takes over the browser's owner id, or starts a new owner
+    let new_owner = {
+        let mut session = session.write().unwrap();
+        if session.owner.is_empty() {
+            match jar.get(OWNER_COOKIE) {
+                Some(cookie) => session.owner = cookie.value().to_string(),
+                None => session.owner = generate_id(),
+            }
+        }
+        let known = jar
+            .get(OWNER_COOKIE)
+            .is_some_and(|cookie| cookie.value() == session.owner);
+        (!known).then(|| session.owner.clone())
+    };
+
+    request.extensions_mut().insert(session);
+    let response = next.run(request).await;
+
+    let mut jar = jar;
+    if created {
+        jar = jar.add(
+            Cookie::build((SESSION_COOKIE, id))
+                .path("/")
+                .http_only(true)
+                .same_site(SameSite::Lax),
+        );
+    }
+    if let Some(owner) = new_owner {
+        jar = jar.add(
+            Cookie::build((OWNER_COOKIE, owner))
+                .path("/")
+                .http_only(true)
+                .same_site(SameSite::Lax)
+                .permanent(),
+        );
+    }
+    (jar, response).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sessions_are_isolated() {
+        let store = SessionStore::default();
+        let (first_id, first, created) = store.get_or_create(None);
+        assert!(created);
+        let (second_id, second, _) = store.get_or_create(None);
+        assert_ne!(first_id, second_id);
+
+        first.write().unwrap().game = Some(GameState::default());
+        assert!(second.read().unwrap().game.is_none());
+
+        let (same_id, same, created) = store.get_or_create(Some(&first_id));
+        assert!(!created);
+        assert_eq!(same_id, first_id);
+        assert!(same.read().unwrap().game.is_some());
+    }
+
+    #[test]
+    fn test_flash_is_shown_once() {
+        let mut session = Session::default();
+        session.flash_result(Ok::<(), String>(()), "Built a farm".to_string());
+        session.flash_result(Err("Not enough gold!"), String::new());
+
+        let flash = session.take_flash();
+        assert_eq!(flash.len(), 2);
+        assert_eq!(flash[0].kind, FlashKind::Success);
+        assert_eq!(flash[1].message, "Not enough gold!");
+        assert!(session.take_flash().is_empty());
+    }
+
+    #[test]
+    fn test_unknown_id_gets_new_session() {
+        let store = SessionStore::default();
+        let (id, _, created) = store.get_or_create(Some("forged"));
+        assert!(created);
+        assert_ne!(id, "forged");
+    }
+
+    #[test]
+    fn test_expired_id_gets_new_session() {
+        let store = SessionStore::default();
+        let (id, old, _) = store.get_or_create(None);
+        old.write().unwrap().game = Some(GameState::default());
+
+        let (new_id, new, created) = store.get_or_create_within(Some(&id), Duration::ZERO);
+        assert!(created);
+        assert_ne!(new_id, id);
+        assert!(new.read().unwrap().game.is_none());
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn test_remove_idle() {
+        let store = SessionStore::default();
+        store.get_or_create(None);
+        assert_eq!(store.remove_idle(Duration::from_secs(60)), 0);
+        assert_eq!(store.remove_idle(Duration::ZERO), 1);
+        assert!(store.is_empty());
+    }
+}